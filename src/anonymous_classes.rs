@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+use std::io;
+use std::io::Write;
+use super::class_hierarchy::ClassHierarchy;
+use super::dominator_tree::DominatorTree;
+use super::heap_dump::HeapDump;
+use super::heap_graph::HeapGraph;
+use super::object_space_2_6_0::node::Node;
+use super::totals::Totals;
+
+// Classes without a name, i.e. made by Class.new, Struct.new or singleton classes
+// made by extend and def self. on instances. Sizes are retained sizes, so the
+// method and constant tables and the iseqs only reachable through a class count towards it.
+pub struct AnonymousClasses {
+    by_site: Totals,
+    by_superclass: Totals,
+    by_kind: Totals,
+    generations: HashMap<String, (usize, usize)>,
+}
+
+impl AnonymousClasses {
+    pub fn new(heap_dump: &HeapDump) -> Self {
+        let graph = HeapGraph::new(heap_dump);
+        let dominator_tree = DominatorTree::new(heap_dump, &graph);
+        let hierarchy = ClassHierarchy::new(heap_dump);
+
+        let mut report = AnonymousClasses {
+            by_site: Totals::default(),
+            by_superclass: Totals::default(),
+            by_kind: Totals::default(),
+            generations: HashMap::new(),
+        };
+
+        for node in heap_dump.objects() {
+            let node_class = match node {
+                Node::Class(node_class) if node_class.name.is_none() => node_class,
+                _ => continue
+            };
+            let retained = graph.index_of(node_class.address).map(|index| dominator_tree.retained_size(index)).unwrap_or(0);
+            let site = node.allocation_site().unwrap_or_else(|| String::from("(unknown)"));
+            let superclass = hierarchy.superclass(node_class.address)
+                .map(|superclass| hierarchy.display_name(superclass))
+                .unwrap_or_else(|| String::from("(none)"));
+            let kind = if hierarchy.is_singleton(node_class.address) { "singleton" } else { "anonymous" };
+
+            if let Some(generation) = node_class.generation {
+                let range = report.generations.entry(site.clone()).or_insert((generation, generation));
+                *range = (range.0.min(generation), range.1.max(generation));
+            }
+            report.by_site.add(site, retained);
+            report.by_superclass.add(superclass, retained);
+            report.by_kind.add(String::from(kind), retained);
+        }
+        report
+    }
+
+    pub fn write(&self, out: &mut dyn Write, limit: usize) -> io::Result<()> {
+        Totals::write(out, "Anonymous classes by kind (retained bytes)", &self.by_kind.by_memsize(usize::MAX))?;
+
+        writeln!(out, "Anonymous classes by allocation site (retained bytes):")?;
+        let by_site = self.by_site.by_memsize(limit);
+        if by_site.is_empty() {
+            writeln!(out, "  none")?;
+        }
+        for (site, total) in by_site {
+            // A wide generation range means the site keeps creating classes
+            let generations = match self.generations.get(site) {
+                Some((oldest, newest)) => format!("gen {}-{}", oldest, newest),
+                None => String::from("gen ?")
+            };
+            writeln!(out, "  {:>10} bytes {:>8} classes {:>14}  {}", total.memsize, total.count, generations, site)?;
+        }
+        writeln!(out)?;
+
+        Totals::write(out, "Anonymous classes by superclass (retained bytes)", &self.by_superclass.by_memsize(limit))
+    }
+
+    // Change per allocation site from an earlier dump, largest growth first
+    pub fn write_trend(&self, before: &AnonymousClasses, out: &mut dyn Write, limit: usize) -> io::Result<()> {
+        let mut change = self.by_site.change_since(&before.by_site);
+        change.truncate(limit);
+        Totals::write_change(out, "Anonymous classes by allocation site, change since the earlier dump (retained bytes)", &change)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::totals::Total;
+
+  fn heap_dump(count: usize) -> HeapDump {
+    let mut heap_dump = HeapDump::default();
+    heap_dump.add_line(String::from(r#"{"type":"ROOT", "root":"vm", "references":["0x1"]}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0x1", "type":"CLASS", "name":"Base", "memsize":100, "flags":{}}"#));
+    for index in 0..count {
+      // Each anonymous class owns a method entry only reachable through it, marked before its super
+      let class = 0x100 + index * 2;
+      heap_dump.add_line(format!(r#"{{"address":"0x{:x}", "type":"CLASS", "references":["0x{:x}", "0x1"], "memsize":200, "file":"app.rb", "line":3, "generation":{}, "flags":{{}}}}"#, class, class + 1, index + 1));
+      heap_dump.add_line(format!(r#"{{"address":"0x{:x}", "type":"IMEMO", "imemo_type":"ment", "memsize":50, "flags":{{}}}}"#, class + 1));
+      heap_dump.add_line(format!(r#"{{"type":"ROOT", "root":"vm", "references":["0x{:x}"]}}"#, class));
+    }
+    heap_dump
+  }
+
+  #[test]
+  fn it_groups_anonymous_classes() {
+    let heap_dump = heap_dump(2);
+    let report = AnonymousClasses::new(&heap_dump);
+
+    assert_eq!(report.by_site.by_memsize(10), vec![(&String::from("app.rb:3"), &Total { count: 2, memsize: 500 })]);
+    assert_eq!(report.by_superclass.by_memsize(10), vec![(&String::from("Base"), &Total { count: 2, memsize: 500 })]);
+    assert_eq!(report.generations.get("app.rb:3"), Some(&(1, 2)));
+  }
+
+  #[test]
+  fn it_computes_the_trend() {
+    let before = AnonymousClasses::new(&heap_dump(1));
+    let after = AnonymousClasses::new(&heap_dump(3));
+
+    assert_eq!(after.by_site.change_since(&before.by_site), vec![(String::from("app.rb:3"), 2, 500)]);
+  }
+}
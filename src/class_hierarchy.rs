@@ -0,0 +1,275 @@
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::io::Write;
+use super::heap_address::HeapAddress;
+use super::heap_dump::HeapDump;
+use super::object_space_2_6_0::node::Node;
+use super::totals::Totals;
+
+#[derive(PartialEq, Debug)]
+pub enum AncestorKind {
+    Class,
+    Prepended,
+    Included,
+}
+
+#[derive(PartialEq, Debug)]
+pub struct Ancestor {
+    pub address: HeapAddress,
+    pub name: String,
+    pub kind: AncestorKind,
+}
+
+// Superclass chains rebuilt from CLASS, MODULE and ICLASS references.
+// A CLASS node's class is its metaclass.
+// The GC marks RCLASS_SUPER last, so the super of a class or iclass is its last
+// reference when that is another class or iclass, other than its own metaclass.
+// Anything else last means no super, as for BasicObject.
+// An ICLASS stands for an included (or prepended) module, which is its "class";
+// an ICLASS whose module is the class itself is that class's origin, created by prepend.
+pub struct ClassHierarchy<'a> {
+    heap_dump: &'a HeapDump,
+    superclasses: HashMap<HeapAddress, HeapAddress>,
+}
+
+impl<'a> ClassHierarchy<'a> {
+    pub fn new(heap_dump: &'a HeapDump) -> Self {
+        let mut superclasses = HashMap::new();
+        for node in heap_dump.objects() {
+            if !matches!(node, Node::Class(_) | Node::Module(_) | Node::Iclass(_)) { continue; }
+            // A module is never a super itself, only its iclasses are
+            let superclass = node.references().last().filter(|reference| {
+                Some(**reference) != node.class() && matches!(heap_dump.get(**reference), Some(Node::Class(_)) | Some(Node::Iclass(_)))
+            });
+            if let Some(superclass) = superclass {
+                superclasses.insert(node.address(), *superclass);
+            }
+        }
+        ClassHierarchy { heap_dump, superclasses }
+    }
+
+    // Addresses of the named classes or modules, reloaded code may leave several
+    pub fn find(&self, name: &str) -> Vec<HeapAddress> {
+        let mut addresses: Vec<HeapAddress> = self.heap_dump.objects()
+            .filter(|node| self.heap_dump.class_name(node.address()) == Some(name))
+            .map(|node| node.address())
+            .collect();
+        addresses.sort_unstable();
+        addresses
+    }
+
+    pub fn display_name(&self, address: HeapAddress) -> String {
+        match (self.heap_dump.class_name(address), self.heap_dump.get(address)) {
+            (Some(name), _) => String::from(name),
+            (None, Some(Node::Module(_))) => format!("#<Module:0x{:x}>", address),
+            (None, _) => format!("#<Class:0x{:x}>", address)
+        }
+    }
+
+    pub fn is_singleton(&self, address: HeapAddress) -> bool {
+        match self.heap_dump.get(address) {
+            Some(Node::Class(node_class)) => node_class.singleton.unwrap_or(false),
+            _ => false
+        }
+    }
+
+    // Ancestors in method resolution order, like Module#ancestors
+    pub fn ancestors(&self, address: HeapAddress) -> Vec<Ancestor> {
+        let mut chain = vec![address];
+        let mut visited: HashSet<HeapAddress> = chain.iter().cloned().collect();
+        let mut current = address;
+        while let Some(superclass) = self.superclasses.get(&current) {
+            if !visited.insert(*superclass) { break; }
+            chain.push(*superclass);
+            current = *superclass;
+        }
+
+        let mut ancestors = vec![];
+        let mut position = 0;
+        while position < chain.len() {
+            let owner = chain[position];
+            // Without an origin iclass nothing was prepended and the owner stands in for it
+            let origin = chain[position + 1..].iter()
+                .position(|entry| self.iclass_module(*entry) == Some(owner))
+                .map(|offset| position + 1 + offset)
+                .unwrap_or(position);
+
+            for entry in chain.iter().take(origin).skip(position + 1) {
+                if let Some(module) = self.iclass_module(*entry) {
+                    ancestors.push(self.ancestor(module, AncestorKind::Prepended));
+                }
+            }
+            ancestors.push(self.ancestor(owner, AncestorKind::Class));
+
+            position = origin + 1;
+            while let Some(module) = chain.get(position).and_then(|entry| self.iclass_module(*entry)) {
+                ancestors.push(self.ancestor(module, AncestorKind::Included));
+                position += 1;
+            }
+        }
+        ancestors
+    }
+
+    fn iclass_module(&self, address: HeapAddress) -> Option<HeapAddress> {
+        match self.heap_dump.get(address) {
+            Some(Node::Iclass(node_iclass)) => Some(node_iclass.class),
+            _ => None
+        }
+    }
+
+    fn ancestor(&self, address: HeapAddress, kind: AncestorKind) -> Ancestor {
+        Ancestor { address, name: self.display_name(address), kind }
+    }
+
+    // First real class in the superclass chain, skipping module iclasses
+    pub fn superclass(&self, address: HeapAddress) -> Option<HeapAddress> {
+        let mut current = *self.superclasses.get(&address)?;
+        let mut visited = HashSet::new();
+        while self.iclass_module(current).is_some() {
+            if !visited.insert(current) { return None; }
+            current = *self.superclasses.get(&current)?;
+        }
+        Some(current)
+    }
+
+    pub fn subclasses(&self, address: HeapAddress) -> Vec<HeapAddress> {
+        let mut subclasses: Vec<HeapAddress> = self.heap_dump.objects()
+            .filter(|node| matches!(node, Node::Class(_)) && self.superclass(node.address()) == Some(address))
+            .map(|node| node.address())
+            .collect();
+        subclasses.sort_unstable();
+        subclasses
+    }
+
+    // Iclasses directly between a class and its superclass, i.e. modules it included itself
+    pub fn own_iclasses(&self, address: HeapAddress) -> usize {
+        let mut count = 0;
+        let mut current = address;
+        while let Some(superclass) = self.superclasses.get(&current) {
+            if self.iclass_module(*superclass).is_none() || count > self.superclasses.len() { break; }
+            count += 1;
+            current = *superclass;
+        }
+        count
+    }
+
+    pub fn write_ancestors(&self, out: &mut dyn Write, name: &str) -> io::Result<()> {
+        let addresses = self.find(name);
+        if addresses.is_empty() {
+            writeln!(out, "No class or module named {}", name)?;
+        }
+        for address in addresses {
+            writeln!(out, "{} (0x{:x}):", name, address)?;
+            for ancestor in self.ancestors(address) {
+                let kind = match ancestor.kind {
+                    AncestorKind::Class => "",
+                    AncestorKind::Prepended => " (prepended)",
+                    AncestorKind::Included => " (included)",
+                };
+                writeln!(out, "  {}{}", ancestor.name, kind)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn write_subclasses(&self, out: &mut dyn Write, name: &str) -> io::Result<()> {
+        let addresses = self.find(name);
+        if addresses.is_empty() {
+            writeln!(out, "No class named {}", name)?;
+        }
+        for address in addresses {
+            let subclasses = self.subclasses(address);
+            writeln!(out, "{} (0x{:x}): {} subclasses", name, address, subclasses.len())?;
+            for subclass in subclasses {
+                let singleton = if self.is_singleton(subclass) { " (singleton)" } else { "" };
+                writeln!(out, "  {} (0x{:x}){}", self.display_name(subclass), subclass, singleton)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Modules included or extended very often, and classes with the most included
+    // modules. Calling extend on every new instance shows up at the top of both.
+    pub fn write_iclasses(&self, out: &mut dyn Write, limit: usize) -> io::Result<()> {
+        let modules = Totals::by(self.heap_dump, |node| match node {
+            Node::Iclass(node_iclass) => Some(self.display_name(node_iclass.class)),
+            _ => None
+        });
+        Totals::write(out, "Modules by iclass count (times included, prepended or extended)", &modules.by_count(limit))?;
+
+        let mut classes: Vec<(usize, HeapAddress)> = self.heap_dump.objects()
+            .filter(|node| matches!(node, Node::Class(_)))
+            .map(|node| (self.own_iclasses(node.address()), node.address()))
+            .filter(|(count, _)| *count > 0)
+            .collect();
+        classes.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+
+        writeln!(out, "Classes by own iclass count:")?;
+        for (count, address) in classes.into_iter().take(limit) {
+            let singleton = if self.is_singleton(address) { " (singleton)" } else { "" };
+            writeln!(out, "  {:>8} iclasses  {} (0x{:x}){}", count, self.display_name(address), address, singleton)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // class Base; class Child < Base; include Mixin; prepend Front; end
+  fn heap_dump() -> HeapDump {
+    let mut heap_dump = HeapDump::default();
+    for line in &[
+      r#"{"address":"0x1", "type":"CLASS", "name":"Base", "class":"0x9", "references":["0x9"], "memsize":0, "flags":{}}"#,
+      r#"{"address":"0x2", "type":"CLASS", "name":"Child", "class":"0x9", "references":["0x9", "0x1", "0x5"], "memsize":0, "flags":{}}"#,
+      r#"{"address":"0x3", "type":"MODULE", "name":"Mixin", "memsize":0, "flags":{}}"#,
+      r#"{"address":"0x4", "type":"MODULE", "name":"Front", "memsize":0, "flags":{}}"#,
+      r#"{"address":"0x5", "type":"ICLASS", "class":"0x4", "references":["0x4", "0x6"], "memsize":0}"#,
+      r#"{"address":"0x6", "type":"ICLASS", "class":"0x2", "references":["0x2", "0x7"], "memsize":0}"#,
+      r#"{"address":"0x7", "type":"ICLASS", "class":"0x3", "references":["0x3", "0x1"], "memsize":0}"#,
+      r#"{"address":"0x9", "type":"CLASS", "name":"Class", "memsize":0, "flags":{}}"#,
+    ] {
+      heap_dump.add_line(String::from(*line));
+    }
+    heap_dump
+  }
+
+  #[test]
+  fn it_reconstructs_ancestors() {
+    let heap_dump = heap_dump();
+    let hierarchy = ClassHierarchy::new(&heap_dump);
+
+    let ancestors: Vec<(String, AncestorKind)> = hierarchy.ancestors(0x2).into_iter().map(|ancestor| (ancestor.name, ancestor.kind)).collect();
+    assert_eq!(ancestors, vec![
+      (String::from("Front"), AncestorKind::Prepended),
+      (String::from("Child"), AncestorKind::Class),
+      (String::from("Mixin"), AncestorKind::Included),
+      (String::from("Base"), AncestorKind::Class),
+    ]);
+  }
+
+  #[test]
+  fn it_finds_subclasses() {
+    let heap_dump = heap_dump();
+    let hierarchy = ClassHierarchy::new(&heap_dump);
+
+    assert_eq!(hierarchy.find("Base"), vec![0x1]);
+    assert_eq!(hierarchy.superclass(0x2), Some(0x1));
+    assert_eq!(hierarchy.subclasses(0x1), vec![0x2]);
+    assert_eq!(hierarchy.own_iclasses(0x2), 3);
+  }
+
+  #[test]
+  fn it_finds_no_superclass_for_root_classes() {
+    let mut heap_dump = heap_dump();
+    // A constant holding a class, then a method entry, and no super marked after them
+    heap_dump.add_line(String::from(r#"{"address":"0xa", "type":"CLASS", "name":"BasicObject", "class":"0x9", "references":["0x1", "0xb"], "memsize":0, "flags":{}}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xb", "type":"IMEMO", "imemo_type":"ment", "memsize":0, "flags":{}}"#));
+    let hierarchy = ClassHierarchy::new(&heap_dump);
+
+    assert_eq!(hierarchy.superclass(0xa), None);
+    assert_eq!(hierarchy.ancestors(0xa), vec![Ancestor { address: 0xa, name: String::from("BasicObject"), kind: AncestorKind::Class }]);
+    assert_eq!(hierarchy.subclasses(0x1), vec![0x2]);
+  }
+}
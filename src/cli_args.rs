@@ -0,0 +1,76 @@
+use std::collections::HashMap;
+
+// Options that consume the following argument as their value
+const VALUE_OPTIONS: &[&str] = &["-o", "--output", "--by", "--top", "--bind", "--pprof", "--compare", "--min-bytes",
+                                 "--reachable-from", "--class", "--site"];
+
+pub struct CliArgs {
+    positional: Vec<String>,
+    options: HashMap<String, String>,
+    switches: Vec<String>,
+}
+
+impl CliArgs {
+    pub fn parse(args: Vec<String>) -> Self {
+        let mut cli_args = CliArgs { positional: vec![], options: HashMap::new(), switches: vec![] };
+        let mut iter = args.into_iter();
+
+        while let Some(arg) = iter.next() {
+            if let Some(split_at) = arg.find('=').filter(|_| arg.starts_with("--")) {
+                let (name, value) = arg.split_at(split_at);
+                cli_args.options.insert(String::from(name), String::from(&value[1..]));
+            } else if VALUE_OPTIONS.contains(&arg.as_str()) {
+                let value = iter.next().unwrap_or_default();
+                cli_args.options.insert(arg, value);
+            } else if arg.starts_with('-') && arg.len() > 1 {
+                cli_args.switches.push(arg);
+            } else {
+                cli_args.positional.push(arg);
+            }
+        }
+        cli_args
+    }
+
+    pub fn positional(&self, index: usize) -> Option<&str> {
+        self.positional.get(index).map(String::as_str)
+    }
+
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.options.get(name).map(String::as_str)
+    }
+
+    pub fn switch(&self, name: &str) -> bool {
+        self.switches.iter().any(|switch| switch == name)
+    }
+
+    pub fn output(&self) -> Option<&str> {
+        self.value("-o").or_else(|| self.value("--output"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn parse(args: &[&str]) -> CliArgs {
+    CliArgs::parse(args.iter().map(|arg| String::from(*arg)).collect())
+  }
+
+  #[test]
+  fn it_parses_options_and_positionals() {
+    let cli_args = parse(&["flame", "--by", "alloc-site", "dump.json", "-o", "out.folded", "--verbose"]);
+    assert_eq!(cli_args.positional(0), Some("flame"));
+    assert_eq!(cli_args.positional(1), Some("dump.json"));
+    assert_eq!(cli_args.positional(2), None);
+    assert_eq!(cli_args.value("--by"), Some("alloc-site"));
+    assert_eq!(cli_args.output(), Some("out.folded"));
+    assert!(cli_args.switch("--verbose"));
+  }
+
+  #[test]
+  fn it_parses_inline_values() {
+    let cli_args = parse(&["flame", "--by=dominators", "dump.json"]);
+    assert_eq!(cli_args.value("--by"), Some("dominators"));
+    assert_eq!(cli_args.positional(1), Some("dump.json"));
+  }
+}
@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+use std::io;
+use std::io::Write;
+use super::dominator_tree::DominatorTree;
+use super::heap_address::HeapAddress;
+use super::heap_dump::HeapDump;
+use super::heap_graph::HeapGraph;
+use super::heap_pages::SLOT_SIZE;
+use super::object_space_2_6_0::node::Node;
+use super::totals::Totals;
+
+// Upper bounds of the element count buckets, anything larger goes in the last one
+const BUCKETS: &[usize] = &[0, 1, 4, 16, 64, 256, 1024];
+
+pub struct Collection {
+    pub address: HeapAddress,
+    pub label: String,
+    pub site: String,
+    pub elements: usize,
+    pub retained: usize,
+}
+
+// Arrays and hashes by element count, to find the ones worth right-sizing:
+// empty ones still holding a heap buffer, huge ones, and hashes whose
+// default proc keeps a closure alive.
+pub struct Collections {
+    histograms: HashMap<String, [usize; BUCKETS.len() + 1]>,
+    empty: Totals,
+    collections: Vec<Collection>,
+    default_procs: Totals,
+}
+
+impl Collections {
+    pub fn new(heap_dump: &HeapDump) -> Self {
+        let graph = HeapGraph::new(heap_dump);
+        let dominator_tree = DominatorTree::new(heap_dump, &graph);
+        let retained = |address| graph.index_of(address).map(|index| dominator_tree.retained_size(index)).unwrap_or(0);
+
+        let mut report = Collections {
+            histograms: HashMap::new(),
+            empty: Totals::default(),
+            collections: vec![],
+            default_procs: Totals::default(),
+        };
+
+        for node in heap_dump.objects() {
+            let elements = match node {
+                Node::Array(node_array) => node_array.length,
+                Node::Hash(node_hash) => node_hash.size,
+                _ => continue
+            };
+            let label = heap_dump.label(node);
+            let site = node.allocation_site().unwrap_or_else(|| String::from("(unknown)"));
+
+            report.histograms.entry(format!("{} {}", label, site)).or_insert([0; BUCKETS.len() + 1])[Self::bucket(elements)] += 1;
+            if elements == 0 && node.memsize() as u64 > SLOT_SIZE {
+                report.empty.add(format!("{} {}", label, site), node.memsize() - SLOT_SIZE as usize);
+            }
+            if let Node::Hash(node_hash) = node {
+                let default_proc = node_hash.default.and_then(|default| heap_dump.get(default))
+                    .filter(|default| default.class().and_then(|class| heap_dump.class_name(class)) == Some("Proc"));
+                if let Some(default_proc) = default_proc {
+                    report.default_procs.add(site.clone(), retained(default_proc.address()));
+                }
+            }
+            report.collections.push(Collection { address: node.address(), label, site, elements, retained: retained(node.address()) });
+        }
+        report
+    }
+
+    fn bucket(elements: usize) -> usize {
+        BUCKETS.iter().position(|bound| elements <= *bound).unwrap_or(BUCKETS.len())
+    }
+
+    fn bucket_name(bucket: usize) -> String {
+        match bucket {
+            0 => String::from("0"),
+            _ if bucket == BUCKETS.len() => format!(">{}", BUCKETS[bucket - 1]),
+            _ if BUCKETS[bucket - 1] + 1 == BUCKETS[bucket] => format!("{}", BUCKETS[bucket]),
+            _ => format!("{}-{}", BUCKETS[bucket - 1] + 1, BUCKETS[bucket])
+        }
+    }
+
+    pub fn largest_by_elements(&self, limit: usize) -> Vec<&Collection> {
+        let mut sorted: Vec<&Collection> = self.collections.iter().collect();
+        sorted.sort_by(|a, b| b.elements.cmp(&a.elements).then(a.address.cmp(&b.address)));
+        sorted.truncate(limit);
+        sorted
+    }
+
+    pub fn largest_by_retained(&self, limit: usize) -> Vec<&Collection> {
+        let mut sorted: Vec<&Collection> = self.collections.iter().collect();
+        sorted.sort_by(|a, b| b.retained.cmp(&a.retained).then(a.address.cmp(&b.address)));
+        sorted.truncate(limit);
+        sorted
+    }
+
+    pub fn write(&self, out: &mut dyn Write, limit: usize) -> io::Result<()> {
+        writeln!(out, "Element count histogram by class and allocation site:")?;
+        write!(out, "  {:>8}", "total")?;
+        for bucket in 0..=BUCKETS.len() {
+            write!(out, " {:>8}", Self::bucket_name(bucket))?;
+        }
+        writeln!(out)?;
+        let mut histograms: Vec<(&String, &[usize; BUCKETS.len() + 1])> = self.histograms.iter().collect();
+        histograms.sort_by(|a, b| b.1.iter().sum::<usize>().cmp(&a.1.iter().sum::<usize>()).then(a.0.cmp(b.0)));
+        for (key, histogram) in histograms.into_iter().take(limit) {
+            write!(out, "  {:>8}", histogram.iter().sum::<usize>())?;
+            for count in histogram.iter() {
+                write!(out, " {:>8}", count)?;
+            }
+            writeln!(out, "  {}", key)?;
+        }
+        writeln!(out)?;
+
+        Totals::write(out, "Empty collections with a heap buffer (bytes beyond the slot)", &self.empty.by_memsize(limit))?;
+
+        writeln!(out, "Largest collections by element count:")?;
+        for collection in self.largest_by_elements(limit) {
+            Self::write_collection(out, collection)?;
+        }
+        writeln!(out)?;
+
+        writeln!(out, "Largest collections by retained size:")?;
+        for collection in self.largest_by_retained(limit) {
+            Self::write_collection(out, collection)?;
+        }
+        writeln!(out)?;
+
+        Totals::write(out, "Hashes with a default proc by allocation site (bytes retained by the proc)", &self.default_procs.by_memsize(limit))
+    }
+
+    fn write_collection(out: &mut dyn Write, collection: &Collection) -> io::Result<()> {
+        writeln!(out, "  {:>10} elements {:>12} bytes  0x{:x} {} {}",
+                 collection.elements, collection.retained, collection.address, collection.label, collection.site)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::totals::Total;
+
+  #[test]
+  fn it_finds_wasteful_collections() {
+    let mut heap_dump = HeapDump::default();
+    heap_dump.add_line(String::from(r#"{"type":"ROOT", "root":"vm", "references":["0xa", "0xb", "0xc"]}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xa", "type":"ARRAY", "length":0, "memsize":200, "file":"app.rb", "line":1}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xb", "type":"ARRAY", "length":3, "references":["0xd"], "memsize":40, "file":"app.rb", "line":1}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xc", "type":"HASH", "size":2, "default":"0xe", "references":["0xe"], "memsize":192, "file":"app.rb", "line":2, "flags":{}}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xd", "type":"STRING", "class":"0x1", "memsize":40, "flags":{}}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xe", "type":"DATA", "class":"0xf", "memsize":80, "flags":{}}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xf", "type":"CLASS", "name":"Proc", "memsize":0, "flags":{}}"#));
+
+    let collections = Collections::new(&heap_dump);
+    assert_eq!(collections.histograms.get("ARRAY app.rb:1"), Some(&[1, 0, 1, 0, 0, 0, 0, 0]));
+    assert_eq!(collections.empty.by_memsize(10), vec![(&String::from("ARRAY app.rb:1"), &Total { count: 1, memsize: 160 })]);
+    assert_eq!(collections.largest_by_elements(1)[0].address, 0xb);
+    // The STRING is no collection, but counts in the retained size of the array holding it
+    assert_eq!(collections.collections.len(), 3);
+    assert_eq!(collections.largest_by_elements(1)[0].retained, 80);
+    assert_eq!(collections.largest_by_retained(1)[0].address, 0xc);
+    assert_eq!(collections.default_procs.by_memsize(10), vec![(&String::from("app.rb:2"), &Total { count: 1, memsize: 80 })]);
+  }
+
+  #[test]
+  fn it_names_buckets() {
+    let names: Vec<String> = (0..=BUCKETS.len()).map(Collections::bucket_name).collect();
+    assert_eq!(names, vec!["0", "1", "2-4", "5-16", "17-64", "65-256", "257-1024", ">1024"]);
+    assert_eq!(Collections::bucket(1025), BUCKETS.len());
+  }
+}
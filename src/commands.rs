@@ -0,0 +1,349 @@
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, BufWriter, Write};
+use std::process;
+use super::anonymous_classes::AnonymousClasses;
+use super::class_hierarchy::ClassHierarchy;
+use super::cli_args::CliArgs;
+use super::deserialize_utils::DeserializeUtils;
+use super::collections_report::Collections;
+use super::cycles_report::CyclesReport;
+use super::data_report::DataReport;
+use super::dump_writer::DumpWriter;
+use super::extract::{Extract, Selection};
+use super::files_report::FilesReport;
+use super::flame_graph::FlameGraph;
+use super::gc_flags::GcFlags;
+use super::heap_dump::HeapDump;
+use super::heap_pages::HeapPages;
+use super::html_report::HtmlReport;
+use super::imemo_report::ImemoReport;
+use super::inspect_report::InspectReport;
+use super::http_server::HttpServer;
+use super::integrity_report::IntegrityReport;
+use super::ivars_report::IvarsReport;
+use super::numerics_report::NumericsReport;
+use super::pprof::PprofProfile;
+use super::procs_report::ProcsReport;
+use super::regexp_report::RegexpReport;
+use super::roots_report::RootsReport;
+use super::strings_report::StringsReport;
+use super::symbols_report::SymbolsReport;
+use super::validator::Validator;
+
+pub const USAGE: &str = "Usage: osn [ruby object space dump]
+       osn flame [--by dominators|alloc-site] [-o output] [ruby object space dump]
+       osn report [--top N] [-o report.html] [ruby object space dump]
+       osn serve [--bind 127.0.0.1:8080] [ruby object space dump]
+       osn export --pprof heap.pb.gz [--per-object] [--class-frame] [ruby object space dump]
+       osn pages [--top N] [ruby object space dump]
+       osn gc-flags [--top N] [ruby object space dump]
+       osn imemo [--top N] [ruby object space dump]
+       osn ancestors <ClassName> [ruby object space dump]
+       osn subclasses <ClassName> [ruby object space dump]
+       osn iclasses [--top N] [ruby object space dump]
+       osn anon-classes [--top N] [--compare earlier dump] [ruby object space dump]
+       osn collections [--top N] [ruby object space dump]
+       osn strings [--top N] [--min-bytes 1024] [ruby object space dump]
+       osn files [ruby object space dump]
+       osn data [--top N] [ruby object space dump]
+       osn procs [--top N] [ruby object space dump]
+       osn symbols [--top N] [--compare earlier dump] [ruby object space dump]
+       osn regexps [--top N] [ruby object space dump]
+       osn numerics [--top N] [ruby object space dump]
+       osn ivars [--top N] [ruby object space dump]
+       osn roots [ruby object space dump]
+       osn cycles [--top N] [ruby object space dump]
+       osn integrity [--top N] [ruby object space dump]
+       osn validate [ruby object space dump]
+       osn inspect <0xaddress> [ruby object space dump]
+       osn rewrite -o output.json [ruby object space dump]
+       osn extract --reachable-from <0xaddress>|--class <ClassName>|--site <file:line> -o output.json [ruby object space dump]
+
+Every command accepts --drop-garbage to leave out objects no root reaches.";
+
+pub struct Commands {}
+
+impl Commands {
+    pub fn flame(args: &CliArgs) {
+        let heap_dump = Self::load_dump(args, args.positional(1));
+        let flame_graph = match args.value("--by").unwrap_or("dominators") {
+            "dominators" => FlameGraph::by_dominators(&heap_dump),
+            "alloc-site" => FlameGraph::by_allocation_site(&heap_dump),
+            other => Self::fail(&format!("Unknown flame graph grouping '{}', expected dominators or alloc-site", other))
+        };
+        Self::write_output(args, |out| flame_graph.write(out));
+    }
+
+    pub fn report(args: &CliArgs) {
+        let filename = args.positional(1).unwrap_or_default();
+        let heap_dump = Self::load_dump(args, args.positional(1));
+        let report = HtmlReport::new(filename, &heap_dump, Self::limit(args, 50));
+        Self::write_output(args, |out| report.write(out));
+    }
+
+    pub fn serve(args: &CliArgs) {
+        let heap_dump = Self::load_dump(args, args.positional(1));
+        let server = HttpServer::new(&heap_dump);
+        if let Err(error) = server.serve(args.value("--bind").unwrap_or("127.0.0.1:8080")) {
+            Self::fail(&format!("Server failure ({:?})", error));
+        }
+    }
+
+    pub fn export(args: &CliArgs) {
+        let path = match args.value("--pprof") {
+            Some(path) => path,
+            None => Self::fail(USAGE)
+        };
+        let heap_dump = Self::load_dump(args, args.positional(1));
+        let mut profile = PprofProfile::new(&heap_dump, args.switch("--per-object"), args.switch("--class-frame"));
+        let result = File::create(path).and_then(|file| profile.write(&mut BufWriter::new(file), path.ends_with(".gz")));
+        if let Err(error) = result {
+            Self::fail(&format!("Output write failure ({:?})", error));
+        }
+    }
+
+    pub fn pages(args: &CliArgs) {
+        let heap_dump = Self::load_dump(args, args.positional(1));
+        let pages = HeapPages::new(&heap_dump);
+        Self::write_output(args, |out| pages.write(out, Self::limit(args, 20)));
+    }
+
+    pub fn gc_flags(args: &CliArgs) {
+        let heap_dump = Self::load_dump(args, args.positional(1));
+        let gc_flags = GcFlags::new(&heap_dump);
+        Self::write_output(args, |out| gc_flags.write(out, Self::limit(args, 20)));
+    }
+
+    pub fn imemo(args: &CliArgs) {
+        let heap_dump = Self::load_dump(args, args.positional(1));
+        let report = ImemoReport::new(&heap_dump);
+        Self::write_output(args, |out| report.write(out, Self::limit(args, 20)));
+    }
+
+    pub fn ancestors(args: &CliArgs) {
+        let name = args.positional(1).unwrap_or_else(|| Self::fail(USAGE));
+        let heap_dump = Self::load_dump(args, args.positional(2));
+        let hierarchy = ClassHierarchy::new(&heap_dump);
+        Self::write_output(args, |out| hierarchy.write_ancestors(out, name));
+    }
+
+    pub fn subclasses(args: &CliArgs) {
+        let name = args.positional(1).unwrap_or_else(|| Self::fail(USAGE));
+        let heap_dump = Self::load_dump(args, args.positional(2));
+        let hierarchy = ClassHierarchy::new(&heap_dump);
+        Self::write_output(args, |out| hierarchy.write_subclasses(out, name));
+    }
+
+    pub fn iclasses(args: &CliArgs) {
+        let heap_dump = Self::load_dump(args, args.positional(1));
+        let hierarchy = ClassHierarchy::new(&heap_dump);
+        Self::write_output(args, |out| hierarchy.write_iclasses(out, Self::limit(args, 20)));
+    }
+
+    pub fn anon_classes(args: &CliArgs) {
+        let heap_dump = Self::load_dump(args, args.positional(1));
+        let report = AnonymousClasses::new(&heap_dump);
+        let before = args.value("--compare").map(|filename| AnonymousClasses::new(&Self::load_dump(args, Some(filename))));
+        Self::write_output(args, |out| {
+            report.write(out, Self::limit(args, 20))?;
+            match &before {
+                Some(before) => report.write_trend(before, out, Self::limit(args, 20)),
+                None => Ok(())
+            }
+        });
+    }
+
+    pub fn collections(args: &CliArgs) {
+        let heap_dump = Self::load_dump(args, args.positional(1));
+        let collections = Collections::new(&heap_dump);
+        Self::write_output(args, |out| collections.write(out, Self::limit(args, 20)));
+    }
+
+    pub fn strings(args: &CliArgs) {
+        let min_bytes = match args.value("--min-bytes").map(|value| value.parse::<usize>()) {
+            Some(Ok(min_bytes)) => min_bytes,
+            Some(Err(_)) => Self::fail("--min-bytes expects a number"),
+            None => 1024
+        };
+        let heap_dump = Self::load_dump(args, args.positional(1));
+        let report = StringsReport::new(&heap_dump, min_bytes);
+        Self::write_output(args, |out| report.write(out, Self::limit(args, 20)));
+    }
+
+    pub fn files(args: &CliArgs) {
+        let heap_dump = Self::load_dump(args, args.positional(1));
+        let report = FilesReport::new(&heap_dump);
+        Self::write_output(args, |out| report.write(out));
+    }
+
+    pub fn data(args: &CliArgs) {
+        let heap_dump = Self::load_dump(args, args.positional(1));
+        let report = DataReport::new(&heap_dump);
+        Self::write_output(args, |out| report.write(out, Self::limit(args, 20)));
+    }
+
+    pub fn procs(args: &CliArgs) {
+        let heap_dump = Self::load_dump(args, args.positional(1));
+        let report = ProcsReport::new(&heap_dump);
+        Self::write_output(args, |out| report.write(out, Self::limit(args, 20)));
+    }
+
+    pub fn symbols(args: &CliArgs) {
+        let heap_dump = Self::load_dump(args, args.positional(1));
+        let report = SymbolsReport::new(&heap_dump);
+        let before_dump = args.value("--compare").map(|filename| Self::load_dump(args, Some(filename)));
+        let before = before_dump.as_ref().map(SymbolsReport::new);
+        Self::write_output(args, |out| {
+            report.write(out, Self::limit(args, 20))?;
+            match &before {
+                Some(before) => report.write_growth(before, out, Self::limit(args, 20)),
+                None => Ok(())
+            }
+        });
+    }
+
+    pub fn regexps(args: &CliArgs) {
+        let heap_dump = Self::load_dump(args, args.positional(1));
+        let report = RegexpReport::new(&heap_dump);
+        Self::write_output(args, |out| report.write(out, Self::limit(args, 20)));
+    }
+
+    pub fn numerics(args: &CliArgs) {
+        let heap_dump = Self::load_dump(args, args.positional(1));
+        let report = NumericsReport::new(&heap_dump);
+        Self::write_output(args, |out| report.write(out, Self::limit(args, 20)));
+    }
+
+    pub fn ivars(args: &CliArgs) {
+        let heap_dump = Self::load_dump(args, args.positional(1));
+        let report = IvarsReport::new(&heap_dump);
+        Self::write_output(args, |out| report.write(out, Self::limit(args, 20)));
+    }
+
+    pub fn roots(args: &CliArgs) {
+        let heap_dump = Self::load_dump(args, args.positional(1));
+        let report = RootsReport::new(&heap_dump);
+        Self::write_output(args, |out| report.write(out));
+    }
+
+    pub fn cycles(args: &CliArgs) {
+        let heap_dump = Self::load_dump(args, args.positional(1));
+        let report = CyclesReport::new(&heap_dump);
+        Self::write_output(args, |out| report.write(out, Self::limit(args, 20)));
+    }
+
+    pub fn integrity(args: &CliArgs) {
+        let heap_dump = Self::load_dump(args, args.positional(1));
+        let report = IntegrityReport::new(&heap_dump);
+        Self::write_output(args, |out| report.write(out, Self::limit(args, 20)));
+    }
+
+    // Exits with 1 when the dump doesn't match the schema
+    pub fn validate(args: &CliArgs) {
+        let filename = args.positional(1).unwrap_or_else(|| Self::fail(USAGE));
+        let validator = match File::open(filename).and_then(|file| Validator::validate(BufReader::new(file))) {
+            Ok(validator) => validator,
+            Err(error) => Self::fail(&format!("File '{}' read failure ({:?})", filename, error))
+        };
+        Self::write_output(args, |out| validator.write(out));
+        if !validator.is_valid() {
+            process::exit(1);
+        }
+    }
+
+    pub fn inspect(args: &CliArgs) {
+        let address = args.positional(1).unwrap_or_else(|| Self::fail(USAGE));
+        let address = DeserializeUtils::hex_to_heap_address(String::from(address))
+            .unwrap_or_else(|| Self::fail("Addresses are hex numbers, e.g. 0x7fc969077fa8"));
+        let heap_dump = Self::load_dump(args, args.positional(2));
+        let report = InspectReport::new(&heap_dump, address)
+            .unwrap_or_else(|| Self::fail(&format!("No object at 0x{:x}", address)));
+        Self::write_output(args, |out| report.write(out));
+    }
+
+    // Writes the loaded dump back out, e.g. without garbage or with duplicate records merged
+    pub fn rewrite(args: &CliArgs) {
+        let heap_dump = Self::load_dump(args, args.positional(1));
+        Self::write_output(args, |out| DumpWriter::write_dump(out, &heap_dump));
+    }
+
+    pub fn extract(args: &CliArgs) {
+        let selection = match (args.value("--reachable-from"), args.value("--class"), args.value("--site")) {
+            (Some(address), None, None) => match DeserializeUtils::hex_to_heap_address(String::from(address)) {
+                Some(address) => Selection::ReachableFrom(address),
+                None => Self::fail("Addresses are hex numbers, e.g. 0x7fc969077fa8")
+            },
+            (None, Some(name), None) => Selection::Class(String::from(name)),
+            (None, None, Some(site)) => Selection::Site(String::from(site)),
+            _ => Self::fail("Select objects with exactly one of --reachable-from, --class or --site")
+        };
+        let mut heap_dump = Self::load_dump(args, args.positional(1));
+        if let Selection::ReachableFrom(address) = selection {
+            if heap_dump.get(address).is_none() {
+                Self::fail(&format!("No object at 0x{:x}", address));
+            }
+        }
+        let extract = Extract::new(&heap_dump, &selection);
+        extract.apply(&mut heap_dump);
+        eprintln!("Extracted {} selected objects, {} with root paths and classes", extract.selected(), extract.addresses().len());
+        Self::write_output(args, |out| DumpWriter::write_dump(out, &heap_dump));
+    }
+
+    // With --drop-garbage, objects no root reaches are left out of every analysis.
+    // Loading messages go to stderr, stdout carries the command's output.
+    pub fn load_dump(args: &CliArgs, filename: Option<&str>) -> HeapDump {
+        Self::load_dump_logging(args, filename, &mut io::stderr())
+    }
+
+    pub fn load_dump_logging(args: &CliArgs, filename: Option<&str>, log: &mut dyn Write) -> HeapDump {
+        let filename = match filename {
+            Some(filename) => filename,
+            None => Self::fail(USAGE)
+        };
+        let mut heap_dump = match File::open(filename) {
+            Ok(file) => HeapDump::load_file(file, log),
+            Err(error) => Self::fail(&format!("File '{}' read failure ({:?})", filename, error))
+        };
+        if args.switch("--drop-garbage") {
+            let (count, memsize) = heap_dump.drop_unreachable();
+            eprintln!("Dropped {} unreachable objects ({} bytes)", count, memsize);
+        }
+        heap_dump
+    }
+
+    pub fn limit(args: &CliArgs, default: usize) -> usize {
+        match args.value("--top").map(|value| value.parse::<usize>()) {
+            Some(Ok(limit)) => limit,
+            Some(Err(_)) => Self::fail("--top expects a number"),
+            None => default
+        }
+    }
+
+    // Writes to the -o/--output file when given, stdout otherwise
+    pub fn write_output<F>(args: &CliArgs, write: F)
+    where
+        F: FnOnce(&mut dyn Write) -> io::Result<()>,
+    {
+        let result = match args.output() {
+            Some(path) => File::create(path).and_then(|file| {
+                let mut out = BufWriter::new(file);
+                write(&mut out)?;
+                out.flush()
+            }),
+            None => {
+                let stdout = io::stdout();
+                let mut out = stdout.lock();
+                write(&mut out)
+            }
+        };
+        if let Err(error) = result {
+            Self::fail(&format!("Output write failure ({:?})", error));
+        }
+    }
+
+    pub fn fail(message: &str) -> ! {
+        eprintln!("{}", message);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,96 @@
+use std::io;
+use std::io::Write;
+use super::heap_address::HeapAddress;
+use super::heap_dump::HeapDump;
+use super::heap_graph::HeapGraph;
+use super::strongly_connected::StronglyConnectedComponents;
+use super::totals::Totals;
+
+// Classes listed per cycle
+const COMPOSITION_LIMIT: usize = 5;
+
+pub struct Cycle {
+    pub objects: usize,
+    pub memsize: usize,
+    pub composition: Totals,
+    // Lowest address in the cycle, to order equally sized ones
+    pub first_address: HeapAddress,
+    component: Vec<usize>,
+}
+
+// Groups of objects that all reference each other. The GC collects them fine,
+// but a large one is usually a cache or observer registry nobody ever clears.
+// Every class forms one with its singleton class, so example cycles are only
+// searched for the cycles printed.
+pub struct CyclesReport<'a> {
+    heap_dump: &'a HeapDump,
+    graph: HeapGraph,
+    cycles: Vec<Cycle>,
+}
+
+impl<'a> CyclesReport<'a> {
+    pub fn new(heap_dump: &'a HeapDump) -> Self {
+        let graph = HeapGraph::new(heap_dump);
+        let components = StronglyConnectedComponents::new(&graph);
+
+        let mut cycles: Vec<Cycle> = components.components().iter().map(|component| {
+            let mut composition = Totals::default();
+            for index in component {
+                if let Some(node) = graph.address(*index).and_then(|address| heap_dump.get(address)) {
+                    composition.add(heap_dump.label(node), node.memsize());
+                }
+            }
+            Cycle {
+                objects: component.len(),
+                memsize: component.iter().map(|index| graph.memsize(heap_dump, *index)).sum(),
+                composition,
+                first_address: component.iter().filter_map(|index| graph.address(*index)).min().unwrap_or(0),
+                component: component.clone(),
+            }
+        }).collect();
+        cycles.sort_by(|a, b| b.memsize.cmp(&a.memsize).then(b.objects.cmp(&a.objects)).then(a.first_address.cmp(&b.first_address)));
+        CyclesReport { heap_dump, graph, cycles }
+    }
+
+    pub fn example(&self, cycle: &Cycle) -> Vec<String> {
+        StronglyConnectedComponents::example_cycle(&self.graph, &cycle.component).into_iter()
+            .map(|index| self.graph.describe(self.heap_dump, index))
+            .collect()
+    }
+
+    pub fn write(&self, out: &mut dyn Write, limit: usize) -> io::Result<()> {
+        let objects: usize = self.cycles.iter().map(|cycle| cycle.objects).sum();
+        writeln!(out, "Strongly connected components: {} ({} objects)", self.cycles.len(), objects)?;
+
+        for cycle in self.cycles.iter().take(limit) {
+            writeln!(out)?;
+            writeln!(out, "{} bytes in {} objects:", cycle.memsize, cycle.objects)?;
+            for (label, total) in cycle.composition.by_memsize(COMPOSITION_LIMIT) {
+                writeln!(out, "  {:>10} bytes {:>8} objects  {}", total.memsize, total.count, label)?;
+            }
+            writeln!(out, "  cycle: {}", self.example(cycle).join(" -> "))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_reports_largest_cycles() {
+    let mut heap_dump = HeapDump::default();
+    heap_dump.add_line(String::from(r#"{"address":"0xa", "type":"HASH", "size":1, "references":["0xb"], "memsize":200, "flags":{}}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xb", "type":"ARRAY", "length":1, "references":["0xa"], "memsize":40}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xc", "type":"ARRAY", "length":1, "references":["0xd"], "memsize":40}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xd", "type":"ARRAY", "length":1, "references":["0xc"], "memsize":40}"#));
+
+    let report = CyclesReport::new(&heap_dump);
+    assert_eq!(report.cycles.len(), 2);
+    assert_eq!(report.cycles[0].memsize, 240);
+    assert_eq!(report.cycles[0].composition.by_memsize(10).len(), 2);
+    assert_eq!(report.cycles[0].first_address, 0xa);
+    assert_eq!(report.example(&report.cycles[0]), vec!["HASH 0xa", "ARRAY 0xb", "HASH 0xa"]);
+  }
+}
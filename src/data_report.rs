@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+use std::io;
+use std::io::Write;
+use super::dominator_tree::DominatorTree;
+use super::heap_address::HeapAddress;
+use super::heap_dump::HeapDump;
+use super::heap_graph::HeapGraph;
+use super::heap_pages::SLOT_SIZE;
+use super::object_space_2_6_0::node::Node;
+use super::totals::{Total, Totals};
+
+// T_DATA objects by rb_data_type_t name. Their memsize is the slot plus whatever
+// the extension's dsize function reports, so a memsize no bigger than a slot means
+// the extension reports nothing and its malloc'ed memory is invisible to the dump.
+pub struct DataReport {
+    struct_types: HashMap<String, (Total, usize)>,
+    unreported: Totals,
+    unreported_objects: Vec<(HeapAddress, String, String)>,
+}
+
+impl DataReport {
+    pub fn new(heap_dump: &HeapDump) -> Self {
+        let graph = HeapGraph::new(heap_dump);
+        let dominator_tree = DominatorTree::new(heap_dump, &graph);
+
+        let mut report = DataReport {
+            struct_types: HashMap::new(),
+            unreported: Totals::default(),
+            unreported_objects: vec![],
+        };
+        for node in heap_dump.objects() {
+            let node_data = match node {
+                Node::Data(node_data) => node_data,
+                _ => continue
+            };
+            let struct_type = node_data.struct_type.clone().unwrap_or_else(|| String::from("(untyped)"));
+            let retained = graph.index_of(node_data.address).map(|index| dominator_tree.retained_size(index)).unwrap_or(0);
+
+            let (total, total_retained) = report.struct_types.entry(struct_type.clone()).or_default();
+            total.count += 1;
+            total.memsize += node_data.memsize;
+            *total_retained += retained;
+            if node_data.memsize as u64 <= SLOT_SIZE {
+                report.unreported.add(struct_type.clone(), node_data.memsize);
+                report.unreported_objects.push((node_data.address, struct_type, heap_dump.label(node)));
+            }
+        }
+        report.unreported_objects.sort();
+        report
+    }
+
+    // Struct types with count, memsize and retained size, largest retained first
+    pub fn by_struct_type(&self, limit: usize) -> Vec<(&String, &Total, usize)> {
+        let mut sorted: Vec<(&String, &Total, usize)> = self.struct_types.iter()
+            .map(|(struct_type, (total, retained))| (struct_type, total, *retained))
+            .collect();
+        sorted.sort_by(|a, b| b.2.cmp(&a.2).then(a.0.cmp(b.0)));
+        sorted.truncate(limit);
+        sorted
+    }
+
+    pub fn write(&self, out: &mut dyn Write, limit: usize) -> io::Result<()> {
+        writeln!(out, "DATA objects by struct type:")?;
+        writeln!(out, "  {:>8} {:>12} {:>12}  struct", "count", "memsize", "retained")?;
+        for (struct_type, total, retained) in self.by_struct_type(limit) {
+            writeln!(out, "  {:>8} {:>12} {:>12}  {}", total.count, total.memsize, retained, struct_type)?;
+        }
+        writeln!(out)?;
+
+        Totals::write(out, "Struct types without a reported size (dsize missing or returning 0)", &self.unreported.by_count(limit))?;
+
+        writeln!(out, "Objects without a reported size:")?;
+        for (address, struct_type, label) in self.unreported_objects.iter().take(limit) {
+            writeln!(out, "  0x{:x} {} {}", address, struct_type, label)?;
+        }
+        if self.unreported_objects.len() > limit {
+            writeln!(out, "  ... {} more", self.unreported_objects.len() - limit)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_groups_data_by_struct_type() {
+    let mut heap_dump = HeapDump::default();
+    heap_dump.add_line(String::from(r#"{"type":"ROOT", "root":"vm", "references":["0xa", "0xc"]}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xa", "type":"DATA", "struct":"proc", "references":["0xb"], "memsize":80, "flags":{}}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xb", "type":"STRING", "class":"0x1", "memsize":40, "flags":{}}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xc", "type":"DATA", "struct":"OpenSSL/X509", "memsize":40, "flags":{}}"#));
+
+    let report = DataReport::new(&heap_dump);
+    let by_struct_type = report.by_struct_type(10);
+    assert_eq!(by_struct_type[0], (&String::from("proc"), &Total { count: 1, memsize: 80 }, 120));
+    assert_eq!(by_struct_type[1], (&String::from("OpenSSL/X509"), &Total { count: 1, memsize: 40 }, 40));
+    assert_eq!(report.unreported_objects, vec![(0xc, String::from("OpenSSL/X509"), String::from("DATA"))]);
+  }
+}
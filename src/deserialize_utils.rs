@@ -1,4 +1,6 @@
 use serde::{Deserialize, Deserializer};
+use serde::de::{DeserializeOwned, DeserializeSeed, Error, IntoDeserializer, MapAccess, Visitor};
+use serde::de::value::Error as ValueError;
 use crate::heap_address::HeapAddress;
 
 pub struct DeserializeUtils {}
@@ -47,4 +49,97 @@ impl DeserializeUtils {
         }
         Ok(heap_addresses)
     }
+
+    // Whether a struct deriving Deserialize with a flattened extra map declares
+    // a field. Declared fields are read with typed calls, anything else is
+    // buffered for the flattened map through deserialize_any.
+    pub fn declares_field<T: DeserializeOwned>(field: &str) -> bool {
+        match T::deserialize(FieldProbe { field: Some(field) }) {
+            Err(error) => error.to_string() == DECLARED,
+            Ok(_) => false
+        }
+    }
+}
+
+const DECLARED: &str = "declared field";
+
+// A map with the probed field as its only key
+struct FieldProbe<'a> {
+    field: Option<&'a str>,
+}
+
+impl<'de, 'a> Deserializer<'de> for FieldProbe<'a> {
+    type Error = ValueError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_map(self)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+impl<'de, 'a> MapAccess<'de> for FieldProbe<'a> {
+    type Error = ValueError;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        match self.field.take() {
+            Some(field) => seed.deserialize(field.into_deserializer()).map(Some),
+            None => Ok(None)
+        }
+    }
+
+    fn next_value_seed<S>(&mut self, seed: S) -> Result<S::Value, Self::Error>
+    where
+        S: DeserializeSeed<'de>,
+    {
+        seed.deserialize(ValueProbe)
+    }
+}
+
+// Fails every typed read with DECLARED, and reads as unit otherwise
+struct ValueProbe;
+
+macro_rules! declared {
+    ($($method:ident($($arg:ty),*))*) => {
+        $(fn $method<V>(self, $(_: $arg,)* _visitor: V) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>,
+        {
+            Err(ValueError::custom(DECLARED))
+        })*
+    };
+}
+
+impl<'de> Deserializer<'de> for ValueProbe {
+    type Error = ValueError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    declared! {
+        deserialize_bool() deserialize_i8() deserialize_i16() deserialize_i32() deserialize_i64()
+        deserialize_i128() deserialize_u8() deserialize_u16() deserialize_u32() deserialize_u64()
+        deserialize_u128() deserialize_f32() deserialize_f64() deserialize_char() deserialize_str()
+        deserialize_string() deserialize_bytes() deserialize_byte_buf() deserialize_option()
+        deserialize_unit() deserialize_unit_struct(&'static str)
+        deserialize_newtype_struct(&'static str) deserialize_seq() deserialize_tuple(usize)
+        deserialize_tuple_struct(&'static str, usize) deserialize_map()
+        deserialize_struct(&'static str, &'static [&'static str])
+        deserialize_enum(&'static str, &'static [&'static str])
+        deserialize_identifier() deserialize_ignored_any()
+    }
 }
\ No newline at end of file
@@ -0,0 +1,163 @@
+use super::heap_dump::HeapDump;
+use super::heap_graph::{HeapGraph, SUPER_ROOT};
+
+const UNDEFINED: usize = usize::MAX;
+
+// Immediate dominators of every vertex reachable from the synthetic root,
+// computed with the iterative Cooper-Harvey-Kennedy algorithm.
+// An object's retained size is the memsize of everything it dominates,
+// i.e. what the GC could free if that object became unreachable.
+pub struct DominatorTree {
+    idom: Vec<usize>,
+    retained: Vec<usize>,
+}
+
+impl DominatorTree {
+    pub fn new(heap_dump: &HeapDump, graph: &HeapGraph) -> Self {
+        let order = Self::reverse_postorder(graph);
+        let mut postorder_number = vec![UNDEFINED; graph.len()];
+        for (position, index) in order.iter().enumerate() {
+            postorder_number[*index] = order.len() - 1 - position;
+        }
+
+        let predecessors = graph.predecessors();
+        let mut idom = vec![UNDEFINED; graph.len()];
+        idom[SUPER_ROOT] = SUPER_ROOT;
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for index in order.iter().skip(1) {
+                let mut new_idom = UNDEFINED;
+                for predecessor in &predecessors[*index] {
+                    if idom[*predecessor] == UNDEFINED { continue; }
+                    new_idom = match new_idom {
+                        UNDEFINED => *predecessor,
+                        current => Self::intersect(&idom, &postorder_number, *predecessor, current)
+                    };
+                }
+                if idom[*index] != new_idom {
+                    idom[*index] = new_idom;
+                    changed = true;
+                }
+            }
+        }
+
+        let mut retained: Vec<usize> = (0..graph.len()).map(|index| graph.memsize(heap_dump, index)).collect();
+        for index in order.iter().skip(1).rev() {
+            retained[idom[*index]] += retained[*index];
+        }
+
+        DominatorTree { idom, retained }
+    }
+
+    fn reverse_postorder(graph: &HeapGraph) -> Vec<usize> {
+        let mut visited = vec![false; graph.len()];
+        let mut postorder = Vec::with_capacity(graph.len());
+        let mut stack = vec![(SUPER_ROOT, 0)];
+        visited[SUPER_ROOT] = true;
+
+        while let Some((index, child)) = stack.pop() {
+            match graph.successors(index).get(child) {
+                Some(successor) => {
+                    stack.push((index, child + 1));
+                    if !visited[*successor] {
+                        visited[*successor] = true;
+                        stack.push((*successor, 0));
+                    }
+                }
+                None => postorder.push(index)
+            }
+        }
+
+        postorder.reverse();
+        postorder
+    }
+
+    fn intersect(idom: &[usize], postorder_number: &[usize], mut first: usize, mut second: usize) -> usize {
+        while first != second {
+            while postorder_number[first] < postorder_number[second] { first = idom[first]; }
+            while postorder_number[second] < postorder_number[first] { second = idom[second]; }
+        }
+        first
+    }
+
+    pub fn is_reachable(&self, index: usize) -> bool {
+        self.idom[index] != UNDEFINED
+    }
+
+    pub fn idom(&self, index: usize) -> Option<usize> {
+        match self.idom[index] {
+            UNDEFINED => None,
+            _ if index == SUPER_ROOT => None,
+            parent => Some(parent)
+        }
+    }
+
+    // Zero for objects not reachable from any root
+    pub fn retained_size(&self, index: usize) -> usize {
+        if self.is_reachable(index) { self.retained[index] } else { 0 }
+    }
+
+    // Dominators of the vertex, outermost first, ending with the vertex itself.
+    // The synthetic root is left out.
+    pub fn chain(&self, index: usize) -> Vec<usize> {
+        let mut chain = vec![];
+        let mut current = Some(index);
+        while let Some(vertex) = current {
+            if vertex == SUPER_ROOT { break; }
+            chain.push(vertex);
+            current = self.idom(vertex);
+        }
+        chain.reverse();
+        chain
+    }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn heap_dump(lines: &[&str]) -> HeapDump {
+    let mut heap_dump = HeapDump::default();
+    for line in lines { heap_dump.add_line(String::from(*line)); }
+    heap_dump
+  }
+
+  #[test]
+  fn it_computes_retained_sizes() {
+    // vm -> a -> b -> d, a -> c -> d
+    let heap_dump = heap_dump(&[
+      r#"{"type":"ROOT", "root":"vm", "references":["0xa"]}"#,
+      r#"{"address":"0xa", "type":"ARRAY", "length":2, "references":["0xb", "0xc"], "memsize":10}"#,
+      r#"{"address":"0xb", "type":"ARRAY", "length":1, "references":["0xd"], "memsize":20}"#,
+      r#"{"address":"0xc", "type":"ARRAY", "length":1, "references":["0xd"], "memsize":30}"#,
+      r#"{"address":"0xd", "type":"ARRAY", "length":0, "memsize":40}"#,
+    ]);
+    let graph = HeapGraph::new(&heap_dump);
+    let tree = DominatorTree::new(&heap_dump, &graph);
+
+    let a = graph.index_of(0xa).unwrap();
+    let b = graph.index_of(0xb).unwrap();
+    let d = graph.index_of(0xd).unwrap();
+    assert_eq!(tree.retained_size(a), 100);
+    assert_eq!(tree.retained_size(b), 20);
+    assert_eq!(tree.idom(d), Some(a));
+    assert_eq!(tree.chain(d), vec![1, a, d]);
+  }
+
+  #[test]
+  fn it_leaves_unreachable_objects_out() {
+    let heap_dump = heap_dump(&[
+      r#"{"type":"ROOT", "root":"vm", "references":[]}"#,
+      r#"{"address":"0xa", "type":"ARRAY", "length":0, "memsize":10}"#,
+    ]);
+    let graph = HeapGraph::new(&heap_dump);
+    let tree = DominatorTree::new(&heap_dump, &graph);
+
+    let a = graph.index_of(0xa).unwrap();
+    assert!(!tree.is_reachable(a));
+    assert_eq!(tree.retained_size(a), 0);
+    assert_eq!(tree.idom(a), None);
+  }
+}
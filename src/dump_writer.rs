@@ -0,0 +1,110 @@
+use std::io;
+use std::io::Write;
+use serde::Serialize;
+use serde_json::ser::{Formatter, Serializer};
+use super::heap_dump::HeapDump;
+use super::object_space_2_6_0::node::Node;
+use super::object_space_2_6_0::node_root::NodeRoot;
+
+// Writes nodes back as ObjectSpace.dump_all lines, with Ruby's field order,
+// hex addresses and ", " separators, so that derived dumps (filtered, trimmed,
+// extracted) load in any tool that reads the originals. Fields unknown to the
+// typed structs are written after the known ones.
+pub struct DumpWriter {}
+
+impl DumpWriter {
+    pub fn write_node(out: &mut dyn Write, node: &Node) -> io::Result<()> {
+        node.serialize(&mut Serializer::with_formatter(&mut *out, DumpFormatter))?;
+        writeln!(out)
+    }
+
+    pub fn write_root(out: &mut dyn Write, root: &NodeRoot) -> io::Result<()> {
+        Node::serialize_root(root, &mut Serializer::with_formatter(&mut *out, DumpFormatter))?;
+        writeln!(out)
+    }
+
+    // Roots first, then objects by address
+    pub fn write_dump(out: &mut dyn Write, heap_dump: &HeapDump) -> io::Result<()> {
+        for root in heap_dump.roots() {
+            Self::write_root(out, root)?;
+        }
+        let mut objects: Vec<&Node> = heap_dump.objects().collect();
+        objects.sort_by_key(|node| node.address());
+        for node in objects {
+            Self::write_node(out, node)?;
+        }
+        Ok(())
+    }
+}
+
+// Compact JSON, except for the space dump_all puts after every separating comma
+struct DumpFormatter;
+
+impl Formatter for DumpFormatter {
+    fn begin_array_value<W>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if first { Ok(()) } else { writer.write_all(b", ") }
+    }
+
+    fn begin_object_key<W>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if first { Ok(()) } else { writer.write_all(b", ") }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const LINES: &[&str] = &[
+    r#"{"type":"ROOT", "root":"vm", "references":["0x7fc969077fa8", "0x7fc96907e740"]}"#,
+    r#"{"address":"0x7fc9748b34f0", "type":"STRING", "class":"0x7fc9690cfb90", "frozen":true, "embedded":true, "fstring":true, "bytesize":5, "value":"hello", "encoding":"UTF-8", "file":"app.rb", "line":3, "method":"greet", "generation":12, "memsize":40, "flags":{"wb_protected":true, "old":true, "uncollectible":true, "marked":true}}"#,
+    r#"{"address":"0x7fc9748a3c30", "type":"ARRAY", "class":"0x7fc9690af8e0", "length":2, "embedded":true, "references":["0x7fc96937b0d8", "0x7fc96937b0b0"], "memsize":40, "flags":{"wb_protected":true}}"#,
+    r#"{"address":"0x7fc9748a3c58", "type":"HASH", "class":"0x7fc9690af840", "size":1, "default":"0x7fc96937b100", "references":["0x7fc96937b100"], "memsize":192, "flags":{"wb_protected":true}}"#,
+    r#"{"address":"0x7fc9748a3c80", "type":"FLOAT", "class":"0x7fc9690af7a0", "frozen":true, "value":"1e+100", "memsize":40, "flags":{"wb_protected":true}}"#,
+    r#"{"address":"0x7fc9748a3ca8", "type":"IMEMO", "imemo_type":"iseq", "references":["0x7fc9748b34f0"], "memsize":1216, "flags":{"wb_protected":true, "old":true, "uncollectible":true, "marked":true}}"#,
+    r#"{"address":"0x7fc9748a3cd0", "type":"OBJECT", "class":"0x7fc9690af700", "ivars":3, "references":["0x7fc9748a3c30"], "memsize":40, "flags":{"wb_protected":true}}"#,
+    r#"{"address":"0x7fc9748a3cf8", "type":"DATA", "class":"0x7fc9690af6b0", "struct":"proc", "references":["0x7fc9748a3ca8"], "memsize":80, "flags":{"wb_protected":true}}"#,
+    r#"{"address":"0x7fc9690cfb90", "type":"CLASS", "class":"0x7fc9690cfb68", "name":"String", "references":["0x7fc9690cfbe0"], "memsize":1192, "flags":{"wb_protected":true, "old":true, "uncollectible":true, "marked":true}}"#,
+    r#"{"address":"0x7fc9748a3d48", "type":"SYMBOL", "class":"0x7fc9690af660", "frozen":true, "value":"dynamic_sym", "memsize":40, "flags":{"wb_protected":true}}"#,
+    r#"{"address":"0x7fc9748a3d70", "type":"FILE", "class":"0x7fc9690af610", "fd":-1, "memsize":232, "flags":{"wb_protected":true}}"#,
+    r#"{"address":"0x7fc9748a3d20", "type":"OBJECT", "class":"0x7fc9690af700", "ivars":0, "memsize":40, "flags":{"wb_protected":true}, "shape_id":7, "slot_size":40}"#,
+  ];
+
+  #[test]
+  fn it_round_trips_dump_lines() {
+    for line in LINES {
+      let mut out = vec![];
+      match Node::from_str(line).unwrap() {
+        Node::Root(root) => DumpWriter::write_root(&mut out, &root).unwrap(),
+        node => DumpWriter::write_node(&mut out, &node).unwrap()
+      }
+      assert_eq!(String::from_utf8(out).unwrap(), format!("{}\n", line));
+    }
+  }
+
+  #[test]
+  fn it_writes_loadable_dumps() {
+    let mut heap_dump = HeapDump::default();
+    for line in LINES {
+      heap_dump.add_line(String::from(*line));
+    }
+    let mut out = vec![];
+    DumpWriter::write_dump(&mut out, &heap_dump).unwrap();
+    let written = String::from_utf8(out).unwrap();
+    assert!(written.starts_with(LINES[0]));
+    assert_eq!(written.lines().count(), LINES.len());
+
+    let mut reloaded = HeapDump::default();
+    for line in written.lines() {
+      reloaded.add_line(String::from(line));
+    }
+    for node in heap_dump.objects() {
+      assert_eq!(reloaded.get(node.address()), Some(node));
+    }
+  }
+}
@@ -0,0 +1,165 @@
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use super::heap_address::HeapAddress;
+use super::heap_dump::HeapDump;
+use super::heap_graph::{HeapGraph, RootPaths};
+use super::object_space_2_6_0::node::Node;
+use super::object_space_2_6_0::node_root::NodeRoot;
+
+pub enum Selection {
+    ReachableFrom(HeapAddress),
+    Class(String),
+    Site(String),
+}
+
+// Objects to cut a smaller dump down to: the selected ones, every object on
+// their shortest paths from a root, and the classes and hash defaults of all
+// of those, so that no address field points outside the extract.
+pub struct Extract {
+    selected: usize,
+    addresses: HashSet<HeapAddress>,
+    // Kept objects no root reaches, referenced by a synthetic root so they are not garbage in the extract
+    unrooted: Vec<HeapAddress>,
+}
+
+impl Extract {
+    pub fn new(heap_dump: &HeapDump, selection: &Selection) -> Self {
+        let graph = HeapGraph::new(heap_dump);
+        let paths = RootPaths::new(&graph);
+
+        let selected: Vec<HeapAddress> = match selection {
+            Selection::ReachableFrom(address) => {
+                let mut seen: HashSet<usize> = graph.index_of(*address).into_iter().collect();
+                let mut queue: VecDeque<usize> = seen.iter().cloned().collect();
+                while let Some(index) = queue.pop_front() {
+                    for successor in graph.successors(index) {
+                        if seen.insert(*successor) { queue.push_back(*successor); }
+                    }
+                }
+                seen.into_iter().filter_map(|index| graph.address(index)).collect()
+            },
+            Selection::Class(name) => heap_dump.objects()
+                .filter(|node| node.class().and_then(|class| heap_dump.class_name(class)) == Some(name.as_str()))
+                .map(Node::address)
+                .collect(),
+            Selection::Site(site) => heap_dump.objects()
+                .filter(|node| node.allocation_site().as_ref() == Some(site))
+                .map(Node::address)
+                .collect()
+        };
+
+        let mut addresses = HashSet::new();
+        let mut unrooted = vec![];
+        let mut pending = selected.clone();
+        while let Some(address) = pending.pop() {
+            if addresses.contains(&address) || heap_dump.get(address).is_none() { continue; }
+            // The object itself and the objects before it on its root path, nearest first.
+            // Root paths share prefixes, so the walk stops at the first object already kept.
+            let members: Vec<HeapAddress> = match graph.index_of(address).and_then(|index| paths.path(index)) {
+                Some(path) => path.into_iter().rev().filter_map(|index| graph.address(index)).collect(),
+                None => {
+                    unrooted.push(address);
+                    vec![address]
+                }
+            };
+            for member in members {
+                if !addresses.insert(member) { break; }
+                if let Some(node) = heap_dump.get(member) {
+                    pending.extend(node.class());
+                    if let Node::Hash(node_hash) = node { pending.extend(node_hash.default); }
+                }
+            }
+        }
+        unrooted.sort_unstable();
+        Extract { selected: selected.len(), addresses, unrooted }
+    }
+
+    // Cuts the dump down to the extract, adding an "extract" root for the unrooted objects
+    pub fn apply(&self, heap_dump: &mut HeapDump) {
+        heap_dump.restrict_to(&self.addresses);
+        if !self.unrooted.is_empty() {
+            heap_dump.add_node(Node::Root(NodeRoot {
+                root: String::from("extract"),
+                references: self.unrooted.clone(),
+                extra: BTreeMap::new()
+            }));
+        }
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn addresses(&self) -> &HashSet<HeapAddress> {
+        &self.addresses
+    }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::dump_writer::DumpWriter;
+  use crate::integrity_report::IntegrityReport;
+
+  fn heap_dump() -> HeapDump {
+    let mut heap_dump = HeapDump::default();
+    heap_dump.add_line(String::from(r#"{"type":"ROOT", "root":"vm", "references":["0xa", "0xc"]}"#));
+    heap_dump.add_line(String::from(r#"{"type":"ROOT", "root":"global_tbl", "references":["0xf"]}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xa", "type":"ARRAY", "length":2, "references":["0xb", "0xd"], "memsize":40}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xb", "type":"OBJECT", "class":"0xf", "ivars":1, "references":["0xe"], "memsize":40, "file":"app.rb", "line":3, "flags":{}}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xc", "type":"ARRAY", "length":0, "memsize":40}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xd", "type":"ARRAY", "length":0, "memsize":40}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xe", "type":"ARRAY", "length":0, "memsize":40}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xf", "type":"CLASS", "name":"Foo", "memsize":500, "flags":{}}"#));
+    heap_dump.add_line(String::from(r#"{"type":"ROOT", "root":"global_list", "references":["0x10"]}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0x10", "type":"HASH", "size":0, "default":"0x11", "memsize":40, "file":"app.rb", "line":9, "flags":{}}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0x11", "type":"ARRAY", "length":0, "memsize":40}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0x12", "type":"OBJECT", "class":"0xf", "ivars":0, "memsize":40, "file":"lib.rb", "line":1, "flags":{}}"#));
+    heap_dump
+  }
+
+  fn sorted(addresses: &HashSet<HeapAddress>) -> Vec<HeapAddress> {
+    let mut addresses: Vec<HeapAddress> = addresses.iter().cloned().collect();
+    addresses.sort_unstable();
+    addresses
+  }
+
+  #[test]
+  fn it_selects_objects_with_their_root_paths_and_classes() {
+    let heap_dump = heap_dump();
+    let extract = Extract::new(&heap_dump, &Selection::Class(String::from("Foo")));
+    assert_eq!(extract.selected(), 2);
+    assert_eq!(sorted(extract.addresses()), vec![0xa, 0xb, 0xf, 0x12]);
+
+    let extract = Extract::new(&heap_dump, &Selection::Site(String::from("app.rb:3")));
+    assert_eq!(sorted(extract.addresses()), vec![0xa, 0xb, 0xf]);
+
+    let extract = Extract::new(&heap_dump, &Selection::ReachableFrom(0xb));
+    assert_eq!(extract.selected(), 2);
+    assert_eq!(sorted(extract.addresses()), vec![0xa, 0xb, 0xe, 0xf]);
+
+    // The default is kept although no reference leads to it
+    let extract = Extract::new(&heap_dump, &Selection::Site(String::from("app.rb:9")));
+    assert_eq!(sorted(extract.addresses()), vec![0x10, 0x11]);
+  }
+
+  #[test]
+  fn it_writes_a_self_contained_dump() {
+    let mut heap_dump = heap_dump();
+    let extract = Extract::new(&heap_dump, &Selection::Class(String::from("Foo")));
+    extract.apply(&mut heap_dump);
+
+    let mut out = vec![];
+    DumpWriter::write_dump(&mut out, &heap_dump).unwrap();
+    let written = String::from_utf8(out).unwrap();
+    assert!(written.contains(r#"{"type":"ROOT", "root":"vm", "references":["0xa"]}"#));
+    assert!(written.contains(r#"{"type":"ROOT", "root":"extract", "references":["0x12"]}"#));
+    assert!(written.contains(r#"{"address":"0xa", "type":"ARRAY", "length":2, "references":["0xb"], "memsize":40}"#));
+
+    let mut reloaded = HeapDump::default();
+    for line in written.lines() {
+      reloaded.add_line(String::from(line));
+    }
+    assert_eq!(reloaded.objects().count(), 4);
+    assert_eq!(IntegrityReport::new(&reloaded).garbage(), (0, 0));
+  }
+}
@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+use std::io;
+use std::io::Write;
+use super::heap_address::HeapAddress;
+use super::heap_dump::HeapDump;
+use super::heap_graph::{HeapGraph, RootPaths};
+use super::object_space_2_6_0::node::Node;
+
+pub struct OpenFile {
+    pub address: HeapAddress,
+    pub fd: i64,
+    pub label: String,
+    pub site: String,
+    pub generation: Option<usize>,
+    pub path: Option<String>,
+}
+
+// Live IO objects by file descriptor. Several objects sharing an fd are either
+// dup'ed IOs or leaked ones whose descriptor was closed and reused behind their back.
+pub struct FilesReport {
+    files: Vec<OpenFile>,
+    fd_counts: HashMap<i64, usize>,
+}
+
+impl FilesReport {
+    pub fn new(heap_dump: &HeapDump) -> Self {
+        let graph = HeapGraph::new(heap_dump);
+        let paths = RootPaths::new(&graph);
+
+        let mut files: Vec<OpenFile> = heap_dump.objects()
+            .filter_map(|node| match node {
+                Node::File(node_file) => Some(node_file),
+                _ => None
+            })
+            .map(|node_file| {
+                let node = heap_dump.get(node_file.address).unwrap();
+                let path = graph.index_of(node_file.address).and_then(|index| paths.path(index)).map(|path| {
+                    path.into_iter().map(|index| graph.describe(heap_dump, index)).collect::<Vec<String>>().join(" -> ")
+                });
+                OpenFile {
+                    address: node_file.address,
+                    fd: node_file.fd,
+                    label: heap_dump.label(node),
+                    site: node.allocation_site().unwrap_or_else(|| String::from("(unknown)")),
+                    generation: node_file.generation,
+                    path,
+                }
+            })
+            .collect();
+        files.sort_by(|a, b| a.fd.cmp(&b.fd).then(a.address.cmp(&b.address)));
+
+        let mut fd_counts = HashMap::new();
+        for file in files.iter().filter(|file| file.fd >= 0) {
+            *fd_counts.entry(file.fd).or_insert(0) += 1;
+        }
+        FilesReport { files, fd_counts }
+    }
+
+    pub fn duplicate_fds(&self) -> Vec<i64> {
+        let mut fds: Vec<i64> = self.fd_counts.iter().filter(|(_, count)| **count > 1).map(|(fd, _)| *fd).collect();
+        fds.sort_unstable();
+        fds
+    }
+
+    pub fn write(&self, out: &mut dyn Write) -> io::Result<()> {
+        let open = self.files.iter().filter(|file| file.fd >= 0).count();
+        writeln!(out, "IO objects: {} ({} open, {} closed)", self.files.len(), open, self.files.len() - open)?;
+
+        let duplicates = self.duplicate_fds();
+        if !duplicates.is_empty() {
+            let fds: Vec<String> = duplicates.iter().map(i64::to_string).collect();
+            writeln!(out, "File descriptors held by more than one object (marked *): {}", fds.join(", "))?;
+        }
+        writeln!(out)?;
+
+        for file in &self.files {
+            let fd = if file.fd < 0 { String::from("closed") } else { file.fd.to_string() };
+            let marker = if self.fd_counts.get(&file.fd).cloned().unwrap_or(0) > 1 { "*" } else { " " };
+            let generation = file.generation.map(|generation| generation.to_string()).unwrap_or_else(|| String::from("?"));
+            writeln!(out, "{}{:>7}  0x{:x} {} gen {} {}", marker, fd, file.address, file.label, generation, file.site)?;
+            writeln!(out, "          {}", file.path.as_deref().unwrap_or("(unreachable from roots)"))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_lists_files_by_fd() {
+    let mut heap_dump = HeapDump::default();
+    heap_dump.add_line(String::from(r#"{"type":"ROOT", "root":"vm", "references":["0xa"]}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xa", "type":"ARRAY", "length":2, "references":["0xb", "0xc"], "memsize":40}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xb", "type":"FILE", "class":"0xf", "fd":7, "memsize":232, "file":"log.rb", "line":3, "generation":5}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xc", "type":"FILE", "class":"0xf", "fd":7, "memsize":232}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xd", "type":"FILE", "class":"0xf", "fd":-1, "memsize":232}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xf", "type":"CLASS", "name":"File", "memsize":0, "flags":{}}"#));
+
+    let report = FilesReport::new(&heap_dump);
+    assert_eq!(report.duplicate_fds(), vec![7]);
+    assert_eq!(report.files.iter().map(|file| file.address).collect::<Vec<HeapAddress>>(), vec![0xd, 0xb, 0xc]);
+    assert_eq!(report.files[1].path, Some(String::from("ROOT vm -> ARRAY 0xa -> File 0xb")));
+    assert_eq!(report.files[0].path, None);
+  }
+}
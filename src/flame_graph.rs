@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+use std::io;
+use std::io::Write;
+use super::dominator_tree::DominatorTree;
+use super::heap_dump::HeapDump;
+use super::heap_graph::{HeapGraph, Vertex};
+use super::object_space_2_6_0::node::Node;
+
+// Collapsed stacks ("frame;frame;frame weight") weighted by memsize,
+// as consumed by inferno, flamegraph.pl and speedscope.
+pub struct FlameGraph {
+    stacks: HashMap<String, usize>,
+}
+
+impl FlameGraph {
+    // Stack of every object is its dominator chain, from the root category down
+    pub fn by_dominators(heap_dump: &HeapDump) -> Self {
+        let graph = HeapGraph::new(heap_dump);
+        let tree = DominatorTree::new(heap_dump, &graph);
+        let mut flame_graph = FlameGraph { stacks: HashMap::new() };
+
+        for index in 0..graph.len() {
+            let node = match graph.address(index).and_then(|address| heap_dump.get(address)) {
+                Some(node) => node,
+                None => continue
+            };
+            let frames: Vec<String> = if tree.is_reachable(index) {
+                tree.chain(index).into_iter().map(|vertex| Self::vertex_frame(heap_dump, &graph, vertex)).collect()
+            } else {
+                vec![String::from("(unreachable)"), heap_dump.label(node)]
+            };
+            flame_graph.add(&frames, node.memsize());
+        }
+        flame_graph
+    }
+
+    // Stack of every object is gem, file and method it was allocated in, then its class
+    pub fn by_allocation_site(heap_dump: &HeapDump) -> Self {
+        let mut flame_graph = FlameGraph { stacks: HashMap::new() };
+
+        for node in heap_dump.objects() {
+            let frames = match node.file() {
+                Some(file) => vec![
+                    String::from(Self::gem_name(file).unwrap_or("(no gem)")),
+                    String::from(file),
+                    Self::method_frame(node),
+                    heap_dump.label(node)
+                ],
+                None => vec![String::from("(unknown)"), heap_dump.label(node)]
+            };
+            flame_graph.add(&frames, node.memsize());
+        }
+        flame_graph
+    }
+
+    fn add(&mut self, frames: &[String], weight: usize) {
+        if weight == 0 { return; }
+        let sanitized: Vec<String> = frames.iter().map(|frame| Self::sanitize(frame)).collect();
+        *self.stacks.entry(sanitized.join(";")).or_insert(0) += weight;
+    }
+
+    fn vertex_frame(heap_dump: &HeapDump, graph: &HeapGraph, index: usize) -> String {
+        match graph.vertex(index) {
+            Vertex::SuperRoot => String::from("(roots)"),
+            Vertex::Root(name) => format!("ROOT {}", name),
+            Vertex::Object(address) => match heap_dump.get(*address) {
+                Some(node) => heap_dump.label(node),
+                None => format!("0x{:x}", address)
+            }
+        }
+    }
+
+    fn method_frame(node: &Node) -> String {
+        let method = node.method().unwrap_or("(unknown method)");
+        match node.line() {
+            Some(line) => format!("{}:{}", method, line),
+            None => String::from(method)
+        }
+    }
+
+    // "actionview-5.2.2" for ".../gems/actionview-5.2.2/lib/action_view.rb"
+    pub fn gem_name(file: &str) -> Option<&str> {
+        let start = file.rfind("/gems/")? + "/gems/".len();
+        file[start..].split('/').next().filter(|name| !name.is_empty())
+    }
+
+    // Semicolons separate frames and newlines separate stacks in the folded format
+    fn sanitize(frame: &str) -> String {
+        frame.replace(';', ",").replace('\n', " ")
+    }
+
+    pub fn write(&self, out: &mut dyn Write) -> io::Result<()> {
+        let mut stacks: Vec<(&String, &usize)> = self.stacks.iter().collect();
+        stacks.sort();
+        for (stack, weight) in stacks {
+            writeln!(out, "{} {}", stack, weight)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn heap_dump(lines: &[&str]) -> HeapDump {
+    let mut heap_dump = HeapDump::default();
+    for line in lines { heap_dump.add_line(String::from(*line)); }
+    heap_dump
+  }
+
+  fn folded(flame_graph: &FlameGraph) -> String {
+    let mut out = vec![];
+    flame_graph.write(&mut out).unwrap();
+    String::from_utf8(out).unwrap()
+  }
+
+  #[test]
+  fn it_folds_dominator_chains() {
+    let heap_dump = heap_dump(&[
+      r#"{"type":"ROOT", "root":"vm", "references":["0xa"]}"#,
+      r#"{"address":"0xa", "type":"ARRAY", "length":1, "references":["0xb"], "memsize":10}"#,
+      r#"{"address":"0xb", "type":"STRING", "class":"0xc", "memsize":40, "flags":{}}"#,
+      r#"{"address":"0xc", "type":"CLASS", "name":"String", "memsize":0, "flags":{}}"#,
+    ]);
+    let flame_graph = FlameGraph::by_dominators(&heap_dump);
+    assert_eq!(folded(&flame_graph), "ROOT vm;ARRAY 10\nROOT vm;ARRAY;String 40\n");
+  }
+
+  #[test]
+  fn it_folds_allocation_sites() {
+    let heap_dump = heap_dump(&[
+      r#"{"address":"0xa", "type":"ARRAY", "length":0, "memsize":10, "file":"/gems/rack-2.0.6/lib/rack.rb", "line":3, "method":"call"}"#,
+      r#"{"address":"0xb", "type":"ARRAY", "length":0, "memsize":30, "file":"/gems/rack-2.0.6/lib/rack.rb", "line":3, "method":"call"}"#,
+      r#"{"address":"0xc", "type":"ARRAY", "length":0, "memsize":5}"#,
+    ]);
+    let flame_graph = FlameGraph::by_allocation_site(&heap_dump);
+    assert_eq!(folded(&flame_graph), "(unknown);ARRAY 5\nrack-2.0.6;/gems/rack-2.0.6/lib/rack.rb;call:3;ARRAY 40\n");
+  }
+
+  #[test]
+  fn it_extracts_gem_names() {
+    assert_eq!(FlameGraph::gem_name("/gems/actionview-5.2.2/lib/action_view.rb"), Some("actionview-5.2.2"));
+    assert_eq!(FlameGraph::gem_name("/app/models/user.rb"), None);
+  }
+}
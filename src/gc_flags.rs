@@ -0,0 +1,71 @@
+use std::io;
+use std::io::Write;
+use super::heap_dump::HeapDump;
+use super::object_space_2_6_0::node::Node;
+use super::totals::Totals;
+
+// Generational GC view of the dump. Objects without the wb_protected flag are
+// write-barrier unprotected: once old, the GC can't track their writes and has
+// to rescan them on every minor GC through the remember set.
+pub struct GcFlags {
+    old: Totals,
+    young: Totals,
+    unprotected_by_class: Totals,
+    unprotected_by_site: Totals,
+    young_from_unprotected: Totals,
+}
+
+impl GcFlags {
+    pub fn new(heap_dump: &HeapDump) -> Self {
+        let mut young_from_unprotected = Totals::default();
+        for node in heap_dump.objects().filter(|node| node.is_old() && Self::is_wb_unprotected(node)) {
+            for reference in node.references() {
+                if let Some(referenced) = heap_dump.get(*reference).filter(|referenced| !referenced.is_old()) {
+                    young_from_unprotected.add(heap_dump.label(referenced), referenced.memsize());
+                }
+            }
+        }
+
+        GcFlags {
+            old: Totals::by(heap_dump, |node| Some(heap_dump.label(node)).filter(|_| node.is_old())),
+            young: Totals::by(heap_dump, |node| Some(heap_dump.label(node)).filter(|_| !node.is_old())),
+            unprotected_by_class: Totals::by(heap_dump, |node| Some(heap_dump.label(node)).filter(|_| Self::is_wb_unprotected(node))),
+            unprotected_by_site: Totals::by(heap_dump, |node| node.allocation_site().filter(|_| Self::is_wb_unprotected(node))),
+            young_from_unprotected,
+        }
+    }
+
+    pub fn is_wb_unprotected(node: &Node) -> bool {
+        !node.flags().and_then(|flags| flags.wb_protected).unwrap_or(false)
+    }
+
+    pub fn write(&self, out: &mut dyn Write, limit: usize) -> io::Result<()> {
+        Totals::write(out, "Old objects by class", &self.old.by_memsize(limit))?;
+        Totals::write(out, "Young objects by class", &self.young.by_memsize(limit))?;
+        Totals::write(out, "Write-barrier unprotected objects by class", &self.unprotected_by_class.by_memsize(limit))?;
+        Totals::write(out, "Write-barrier unprotected objects by allocation site", &self.unprotected_by_site.by_memsize(limit))?;
+        Totals::write(out, "Young objects referenced from old unprotected objects", &self.young_from_unprotected.by_memsize(limit))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::totals::Total;
+
+  #[test]
+  fn it_groups_objects_by_gc_flags() {
+    let mut heap_dump = HeapDump::default();
+    heap_dump.add_line(String::from(r#"{"address":"0xa", "type":"DATA", "references":["0xb", "0xc"], "memsize":100, "file":"app.rb", "line":1, "flags":{"old":true}}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xb", "type":"STRING", "class":"0xd", "memsize":40, "flags":{"wb_protected":true}}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xc", "type":"STRING", "class":"0xd", "memsize":40, "flags":{"wb_protected":true, "old":true}}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xd", "type":"CLASS", "name":"String", "memsize":0, "flags":{"wb_protected":true, "old":true}}"#));
+
+    let gc_flags = GcFlags::new(&heap_dump);
+    assert_eq!(gc_flags.old.by_memsize(10).len(), 3);
+    assert_eq!(gc_flags.young.by_memsize(10), vec![(&String::from("String"), &Total { count: 1, memsize: 40 })]);
+    assert_eq!(gc_flags.unprotected_by_class.by_memsize(10), vec![(&String::from("DATA"), &Total { count: 1, memsize: 100 })]);
+    assert_eq!(gc_flags.unprotected_by_site.by_memsize(10), vec![(&String::from("app.rb:1"), &Total { count: 1, memsize: 100 })]);
+    assert_eq!(gc_flags.young_from_unprotected.by_memsize(10), vec![(&String::from("String"), &Total { count: 1, memsize: 40 })]);
+  }
+}
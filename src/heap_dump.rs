@@ -1,276 +1,138 @@
-use serde_json::{Error, Value};
-use serde::{Deserialize, Deserializer};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::prelude::*;
 use std::io::BufReader;
 use super::heap_address::HeapAddress;
+use super::heap_graph::{HeapGraph, RootPaths};
+use super::object_space_2_6_0::node::Node;
+use super::object_space_2_6_0::node_root::NodeRoot;
 
-#[derive(Debug)]
-enum AnyHeapObject {
-    StringObject(StringObject),
-    ArrayObject(ArrayObject),
-    HeapObject(HeapObject),
-    RootObject(RootObject)
+#[derive(Default)]
+pub struct HeapDump {
+    root_objects: HashMap<String, NodeRoot>,
+    objects: HashMap<HeapAddress, Node>,
+    // Addresses seen on more than one line, the last record wins
+    duplicates: Vec<HeapAddress>,
 }
 
-// type HeapAddress = u64;
-
-struct ConversionUtils {}
-impl ConversionUtils {
-    pub fn hex_to_heap_address(value: String) -> Option<HeapAddress> {
-        let hex_number = value.replace("\"", "").replace("0x", "");
+impl HeapDump {
+    // The object count and the lines failing to parse go to log, stdout for the
+    // roots listing and stderr for commands printing their own output there
+    pub fn load_file(file: File, log: &mut dyn Write) -> Self {
+        let mut heap_dump = HeapDump::default();
+        let buf_reader = BufReader::new(file);
 
-        let uvalue = u64::from_str_radix(hex_number.as_ref(), 16);
-        if uvalue.is_err() {
-            return None;
-        }
-        Some(uvalue.unwrap())
-    }
+        for line in buf_reader.lines() { heap_dump.add_line_logging(line.unwrap(), log); }
 
-    pub fn dejsonify_string(value: String) -> String {
-        value.replace("\"", "")
+        let _ = writeln!(log, "Loaded {} objects", heap_dump.objects.len());
+        heap_dump
     }
 
-    pub fn references(references: &Vec<Value>) -> Vec<HeapAddress> {
-        let mut heap_addresses = Vec::with_capacity(references.len());
-
-        for value in references {
-            let address = value.to_string();
-            let heap_address = Self::hex_to_heap_address(address);
-            if heap_address.is_none() {
-                continue;
-            }
-            heap_addresses.push(heap_address.unwrap());
-        }
-
-        heap_addresses
+    #[cfg(test)]
+    pub fn add_line(&mut self, line: String) {
+        self.add_line_logging(line, &mut std::io::stdout())
     }
 
-    pub fn from_hex<'de, D>(deserializer: D) -> Result<HeapAddress, D::Error>
-    where
-        D: Deserializer<'de>,
-    {
-        let value: &str = Deserialize::deserialize(deserializer)?;
-
-        match ConversionUtils::hex_to_heap_address(String::from(value)) {
-            Some(heap_address) => Ok(heap_address),
-            None => Ok(0 as HeapAddress)
+    fn add_line_logging(&mut self, line: String, log: &mut dyn Write) {
+        match Node::from_str(&line) {
+            Ok(node) => self.add_node(node),
+            Err(error) => { let _ = writeln!(log, "Error: {:?} for {}", error, line); }
         }
     }
 
-    pub fn from_hex_array<'de, D>(deserializer: D) -> Result<Vec<HeapAddress>, D::Error>
-    where
-        D: Deserializer<'de>,
-    {
-        let mut heap_addresses : Vec<HeapAddress> = vec!();
-        let values: Vec<&str> = Deserialize::deserialize(deserializer)?;
-
-        for value in values {
-            let hao = ConversionUtils::hex_to_heap_address(String::from(value));
-            if hao.is_none() { continue; }
-            heap_addresses.push(hao.unwrap());
+    pub fn add_node(&mut self, node: Node) {
+        match node {
+            Node::Root(root_object) => {
+                // The same root category may be reported on several lines
+                match self.root_objects.get_mut(&root_object.root) {
+                    Some(existing) => existing.references.extend(root_object.references),
+                    None => { self.root_objects.insert(root_object.root.clone(), root_object); }
+                }
+            }
+            heap_object => {
+                let address = heap_object.address();
+                if self.objects.insert(address, heap_object).is_some() {
+                    self.duplicates.push(address);
+                }
+            }
         }
-        Ok(heap_addresses)
     }
-}
-
-#[derive(Hash, Eq, PartialEq, Debug, Deserialize)]
-struct RootObject {
-    root: String,
-    #[serde(deserialize_with = "ConversionUtils::from_hex_array")]
-    references: Vec<HeapAddress>,
-}
 
-impl RootObject {
-    fn from_str(json_form: &str) -> Result<Self, Error> {
-        serde_json::from_str(json_form)
+    pub fn duplicates(&self) -> &[HeapAddress] {
+        &self.duplicates
     }
 
-    fn from_value(value: Value) -> Self {
-        let references_array = value["references"].as_array();
-
-        let references = match references_array {
-            Some(refs) => ConversionUtils::references(refs),
-            None => vec![],
+    // Removes the objects no root reaches, i.e. garbage left by dumping without
+    // a full GC first. Returns how many objects and bytes were dropped.
+    pub fn drop_unreachable(&mut self) -> (usize, usize) {
+        let reachable: HashSet<HeapAddress> = {
+            let graph = HeapGraph::new(self);
+            let paths = RootPaths::new(&graph);
+            (0..graph.len()).filter(|index| paths.is_reachable(*index)).filter_map(|index| graph.address(index)).collect()
         };
-
-        RootObject {
-            root: ConversionUtils::dejsonify_string(value["root"].to_string()),
-            references: references,
-        }
+        let before = (self.objects.len(), self.objects.values().map(Node::memsize).sum::<usize>());
+        self.objects.retain(|address, _| reachable.contains(address));
+        (before.0 - self.objects.len(), before.1 - self.objects.values().map(Node::memsize).sum::<usize>())
     }
-}
-
-#[derive(Hash, Eq, PartialEq, Debug, Deserialize)]
-struct HeapObject {
-    address: HeapAddress,
-    object_type: String,
-    class: Option<HeapAddress>,
-    references: Vec<HeapAddress>,
-    file: String,
-    line: usize,
-    method: String,
-    generation: usize,
-    memsize: usize,
-}
-
-impl HeapObject {
-    fn from_value(value: Value) -> Option<Self> {
-        let address = ConversionUtils::hex_to_heap_address(value["address"].to_string());
-        let class = ConversionUtils::hex_to_heap_address(value["class"].to_string());
-        let references_array = value["references"].as_array();
 
-        let references = match references_array {
-            Some(refs) => ConversionUtils::references(refs),
-            None => vec![],
-        };
-
-        let heap_object = HeapObject {
-            address: address.unwrap(),
-            object_type: ConversionUtils::dejsonify_string(value["type"].to_string()),
-            class: class,
-            references: references,
-            file: ConversionUtils::dejsonify_string(value["file"].to_string()),
-            line: value["line"].as_u64().unwrap_or_default() as usize,
-            method: ConversionUtils::dejsonify_string(value["method"].to_string()),
-            generation: value["generation"].as_u64().unwrap_or_default() as usize,
-            memsize: value["memsize"].as_u64().unwrap_or_default() as usize,
-        };
-
-        Some(heap_object)
+    // Keeps only the given objects and the references between them, so that the
+    // rest is a self-contained dump. Roots keep their references to kept objects,
+    // root categories left with none are removed.
+    pub fn restrict_to(&mut self, addresses: &HashSet<HeapAddress>) {
+        self.objects.retain(|address, _| addresses.contains(address));
+        for node in self.objects.values_mut() {
+            if let Some(references) = node.references_mut() {
+                references.retain(|reference| addresses.contains(reference));
+            }
+        }
+        for root in self.root_objects.values_mut() {
+            root.references.retain(|reference| addresses.contains(reference));
+        }
+        self.root_objects.retain(|_, root| !root.references.is_empty());
     }
-}
-
-#[derive(Hash, Eq, PartialEq, Debug, Deserialize)]
-struct Flags {
-    wb_protected: Option<bool>,
-    old: Option<bool>,
-    uncollectible: Option<bool>,
-    marked: Option<bool>
-}
 
-#[derive(Hash, Eq, PartialEq, Debug, Deserialize)]
-struct StringObject {
-    #[serde(deserialize_with = "ConversionUtils::from_hex")]
-    address: HeapAddress,
-    #[serde(deserialize_with = "ConversionUtils::from_hex")]
-    #[serde(default)]
-    class: HeapAddress,
-    frozen: Option<bool>,
-    fstring: Option<bool>,
-    bytesize: Option<usize>,
-    capacity: Option<usize>,
-    value: Option<String>,
-    encoding: Option<String>,
-    memsize: usize,
-    #[serde(default)]
-    #[serde(deserialize_with = "ConversionUtils::from_hex_array")]
-    references: Vec<HeapAddress>,
-    flags: Flags,
-    file: Option<String>,
-    generation: Option<usize>,
-    line: Option<usize>
-}
-
-impl StringObject {
-    fn from_str(json_form: &str) -> Result<Self, Error> {
-        serde_json::from_str(json_form)
+    pub fn get(&self, address: HeapAddress) -> Option<&Node> {
+        self.objects.get(&address)
     }
-}
-
-#[derive(Hash, Eq, PartialEq, Debug, Deserialize)]
-struct ArrayObject {
-    #[serde(deserialize_with = "ConversionUtils::from_hex")]
-    address: HeapAddress,
-    #[serde(deserialize_with = "ConversionUtils::from_hex")]
-    #[serde(default)]
-    class: HeapAddress,
-    length: usize,
-    memsize: usize,
-    flags: Option<Flags>,
-    #[serde(default)]
-    #[serde(deserialize_with = "ConversionUtils::from_hex_array")]
-    references: Vec<HeapAddress>,
-}
 
-impl ArrayObject {
-    fn from_str(json_form: &str) -> Result<Self, Error> {
-        serde_json::from_str(json_form)
+    pub fn objects(&self) -> impl Iterator<Item = &Node> {
+        self.objects.values()
     }
-}
-
-pub struct HeapDump {
-    root_objects: HashMap<String, RootObject>,
-    objects: HashMap<HeapAddress, AnyHeapObject>,
-}
-
-impl HeapDump {
-    pub fn load_file(file: File) -> Self {
-        let mut heap_dump = HeapDump { objects: HashMap::new(), root_objects: HashMap::new() };
-        let buf_reader = BufReader::new(file);
 
-        for line in buf_reader.lines() { heap_dump.add_line(line.unwrap()); }
-
-        println!("Loaded {} objects", heap_dump.objects.len());
-        heap_dump
+    // Root categories sorted by name, so reports are stable between runs
+    pub fn roots(&self) -> Vec<&NodeRoot> {
+        let mut roots: Vec<&NodeRoot> = self.root_objects.values().collect();
+        roots.sort_by(|a, b| a.root.cmp(&b.root));
+        roots
     }
 
-    pub fn add_line(&mut self, line: String) {
-        if line.contains("\"type\":\"ROOT\"") {
-            let root_object_result = RootObject::from_str(&line);
-            if root_object_result.is_err() {
-                println!("Root object err {:?}", root_object_result);
-                return;
-            }
-            let root_object = root_object_result.unwrap();
-            self.root_objects.insert(root_object.root.clone(), root_object);
-            return
+    // Name of a CLASS or MODULE object, None for anonymous ones
+    pub fn class_name(&self, address: HeapAddress) -> Option<&str> {
+        match self.objects.get(&address) {
+            Some(Node::Class(node_class)) => node_class.name.as_deref(),
+            Some(Node::Module(node_module)) => node_module.name.as_deref(),
+            _ => None
         }
-        if line.contains("\"type\":\"STRING\"") {
-            let string_object_result = StringObject::from_str(&line);
-            if string_object_result.is_err() {
-                println!("String object err {:?}", string_object_result);
-            } else {
-                let string_object = string_object_result.unwrap();
-                self.objects.insert(string_object.address, AnyHeapObject::StringObject(string_object));
-            }
-            return
-        }
-        if line.contains("\"type\":\"ARRAY\"") {
-            let array_object_result = ArrayObject::from_str(&line);
-            if array_object_result.is_err() {
-                println!("Array object err {:?}", array_object_result);
-            } else {
-                let array_object = array_object_result.unwrap();
-                self.objects.insert(array_object.address, AnyHeapObject::ArrayObject(array_object));
-            }
-            return
-        }
-        let parse_result: Result<Value, Error> = serde_json::from_str(&line);
-
-        if parse_result.is_err() {
-            println!("Error: {:?} for {}", parse_result, line);
-            return;
-        }
-        let value = parse_result.unwrap();
-        self.add_value(value);
     }
 
-    pub fn add_value(&mut self, value: Value) {
-        let heap_object_option = HeapObject::from_value(value);
-        if heap_object_option.is_none() {
-            return;
+    // Human readable object kind: its class name when known, the dump type otherwise.
+    // Named classes and modules are labelled with their own name.
+    pub fn label(&self, node: &Node) -> String {
+        if let Some(name) = self.class_name(node.address()) {
+            return format!("{} ({})", name, node.type_name());
+        }
+        match (node.class().and_then(|class| self.class_name(class)), node) {
+            (Some(name), _) => String::from(name),
+            (None, Node::Imemo(node_imemo)) => format!("IMEMO {}", node_imemo.imemo_type),
+            (None, _) => String::from(node.type_name())
         }
-        let heap_object = heap_object_option.unwrap();
-        self.objects.insert(heap_object.address, AnyHeapObject::HeapObject(heap_object));
     }
 
     pub fn print_roots(&self) {
-        for (_key, root) in &self.root_objects {
+        for root in self.root_objects.values() {
             println!("{:?}", root);
             for address in &root.references {
-                let heap_object_option = self.objects.get(&address);
+                let heap_object_option = self.objects.get(address);
                 if heap_object_option.is_none() {
                     println!("  Missing heap object {:?}", address);
                     continue;
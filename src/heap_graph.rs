@@ -0,0 +1,179 @@
+use std::collections::{HashMap, VecDeque};
+use super::heap_address::HeapAddress;
+use super::heap_dump::HeapDump;
+
+pub const SUPER_ROOT: usize = 0;
+
+#[derive(PartialEq, Debug)]
+pub enum Vertex {
+    SuperRoot,
+    Root(String),
+    Object(HeapAddress),
+}
+
+// Dense, index based view of the dump's reference graph.
+// Vertex 0 is a synthetic root pointing at every root category, root categories
+// point at the objects they reference and objects point at their references.
+// References to addresses missing from the dump are dropped.
+pub struct HeapGraph {
+    vertices: Vec<Vertex>,
+    indices: HashMap<HeapAddress, usize>,
+    successors: Vec<Vec<usize>>,
+}
+
+impl HeapGraph {
+    pub fn new(heap_dump: &HeapDump) -> Self {
+        let roots = heap_dump.roots();
+        let mut addresses: Vec<HeapAddress> = heap_dump.objects().map(|node| node.address()).collect();
+        addresses.sort_unstable();
+
+        let mut vertices = Vec::with_capacity(1 + roots.len() + addresses.len());
+        vertices.push(Vertex::SuperRoot);
+        for root in &roots { vertices.push(Vertex::Root(root.root.clone())); }
+
+        let mut indices = HashMap::with_capacity(addresses.len());
+        for address in addresses {
+            indices.insert(address, vertices.len());
+            vertices.push(Vertex::Object(address));
+        }
+
+        let mut successors = Vec::with_capacity(vertices.len());
+        successors.push((1..=roots.len()).collect());
+        for root in &roots {
+            successors.push(Self::resolve(&indices, &root.references));
+        }
+        for vertex in &vertices[1 + roots.len()..] {
+            if let Vertex::Object(address) = vertex {
+                let references = heap_dump.get(*address).map(|node| node.references()).unwrap_or(&[]);
+                successors.push(Self::resolve(&indices, references));
+            }
+        }
+
+        HeapGraph { vertices, indices, successors }
+    }
+
+    fn resolve(indices: &HashMap<HeapAddress, usize>, references: &[HeapAddress]) -> Vec<usize> {
+        references.iter().filter_map(|address| indices.get(address).cloned()).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.vertices.len()
+    }
+
+    pub fn vertex(&self, index: usize) -> &Vertex {
+        &self.vertices[index]
+    }
+
+    pub fn root_index(&self, name: &str) -> Option<usize> {
+        self.vertices.iter().position(|vertex| match vertex {
+            Vertex::Root(root) => root == name,
+            _ => false
+        })
+    }
+
+    pub fn index_of(&self, address: HeapAddress) -> Option<usize> {
+        self.indices.get(&address).cloned()
+    }
+
+    pub fn address(&self, index: usize) -> Option<HeapAddress> {
+        match self.vertices[index] {
+            Vertex::Object(address) => Some(address),
+            _ => None
+        }
+    }
+
+    pub fn successors(&self, index: usize) -> &[usize] {
+        &self.successors[index]
+    }
+
+    pub fn predecessors(&self) -> Vec<Vec<usize>> {
+        let mut predecessors = vec![vec![]; self.vertices.len()];
+        for (index, successors) in self.successors.iter().enumerate() {
+            for successor in successors { predecessors[*successor].push(index); }
+        }
+        predecessors
+    }
+
+    // One line description of a vertex, as used in textual root paths
+    pub fn describe(&self, heap_dump: &HeapDump, index: usize) -> String {
+        match &self.vertices[index] {
+            Vertex::SuperRoot => String::from("(roots)"),
+            Vertex::Root(name) => format!("ROOT {}", name),
+            Vertex::Object(address) => match heap_dump.get(*address) {
+                Some(node) => format!("{} 0x{:x}", heap_dump.label(node), address),
+                None => format!("0x{:x}", address)
+            }
+        }
+    }
+
+    pub fn memsize(&self, heap_dump: &HeapDump, index: usize) -> usize {
+        self.address(index).and_then(|address| heap_dump.get(address)).map(|node| node.memsize()).unwrap_or(0)
+    }
+}
+
+// Breadth first search tree from the synthetic root, giving the shortest
+// chain of references from any root to every reachable vertex
+pub struct RootPaths {
+    parents: Vec<usize>,
+}
+
+impl RootPaths {
+    pub fn new(graph: &HeapGraph) -> Self {
+        let mut parents = vec![usize::MAX; graph.len()];
+        let mut queue = VecDeque::new();
+        parents[SUPER_ROOT] = SUPER_ROOT;
+        queue.push_back(SUPER_ROOT);
+
+        while let Some(index) = queue.pop_front() {
+            for successor in graph.successors(index) {
+                if parents[*successor] == usize::MAX {
+                    parents[*successor] = index;
+                    queue.push_back(*successor);
+                }
+            }
+        }
+        RootPaths { parents }
+    }
+
+    pub fn is_reachable(&self, index: usize) -> bool {
+        self.parents[index] != usize::MAX
+    }
+
+    // Root category first, ending with the vertex itself. None when unreachable.
+    pub fn path(&self, index: usize) -> Option<Vec<usize>> {
+        if !self.is_reachable(index) { return None; }
+
+        let mut path = vec![];
+        let mut current = index;
+        while current != SUPER_ROOT {
+            path.push(current);
+            current = self.parents[current];
+        }
+        path.reverse();
+        Some(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_finds_shortest_root_paths() {
+    let mut heap_dump = HeapDump::default();
+    heap_dump.add_line(String::from(r#"{"type":"ROOT", "root":"vm", "references":["0xa", "0xc"]}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xa", "type":"ARRAY", "length":1, "references":["0xb", "0xf"], "memsize":10}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xb", "type":"ARRAY", "length":1, "references":["0xc"], "memsize":10}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xc", "type":"ARRAY", "length":0, "memsize":10}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xd", "type":"ARRAY", "length":0, "memsize":10}"#));
+
+    let graph = HeapGraph::new(&heap_dump);
+    let paths = RootPaths::new(&graph);
+    let vm = graph.root_index("vm").unwrap();
+    let c = graph.index_of(0xc).unwrap();
+
+    assert_eq!(graph.successors(graph.index_of(0xa).unwrap()).len(), 1);
+    assert_eq!(paths.path(c), Some(vec![vm, c]));
+    assert_eq!(paths.path(graph.index_of(0xd).unwrap()), None);
+  }
+}
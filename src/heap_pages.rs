@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+use std::io;
+use std::io::Write;
+use super::heap_address::HeapAddress;
+use super::heap_dump::HeapDump;
+use super::object_space_2_6_0::node::Node;
+
+// Ruby 2.6 heap pages are 16 KB aligned blocks of 40 byte RVALUE slots.
+// HEAP_PAGE_SIZE leaves room for the malloc header and the page header,
+// which brings the usable slot count down to 409.
+pub const PAGE_SIZE: u64 = 16 * 1024;
+pub const SLOT_SIZE: u64 = 40;
+pub const SLOTS_PER_PAGE: usize = 409;
+
+const OCCUPANCY_BUCKETS: usize = 10;
+
+pub struct HeapPage<'a> {
+    pub address: HeapAddress,
+    pub objects: Vec<&'a Node>,
+}
+
+impl<'a> HeapPage<'a> {
+    pub fn occupancy(&self) -> f64 {
+        self.objects.len() as f64 / SLOTS_PER_PAGE as f64
+    }
+
+    // Old or uncollectible objects are not going away soon and keep the whole page alive
+    pub fn is_pinned(&self) -> bool {
+        self.objects.iter().any(|node| node.is_old() || node.is_uncollectible())
+    }
+}
+
+pub struct HeapPages<'a> {
+    heap_dump: &'a HeapDump,
+    pages: Vec<HeapPage<'a>>,
+}
+
+impl<'a> HeapPages<'a> {
+    pub fn new(heap_dump: &'a HeapDump) -> Self {
+        let mut pages: HashMap<HeapAddress, Vec<&Node>> = HashMap::new();
+        for node in heap_dump.objects() {
+            pages.entry(Self::page_address(node.address())).or_default().push(node);
+        }
+
+        let mut pages: Vec<HeapPage> = pages.into_iter().map(|(address, objects)| HeapPage { address, objects }).collect();
+        pages.sort_by_key(|page| page.address);
+        HeapPages { heap_dump, pages }
+    }
+
+    pub fn page_address(address: HeapAddress) -> HeapAddress {
+        address & !(PAGE_SIZE - 1)
+    }
+
+    pub fn live_slots(&self) -> usize {
+        self.pages.iter().map(|page| page.objects.len()).sum()
+    }
+
+    // Page counts per 10% occupancy bucket, the last bucket includes full pages
+    pub fn occupancy_histogram(&self) -> [usize; OCCUPANCY_BUCKETS] {
+        let mut histogram = [0; OCCUPANCY_BUCKETS];
+        for page in &self.pages {
+            let bucket = (page.occupancy() * OCCUPANCY_BUCKETS as f64) as usize;
+            histogram[bucket.min(OCCUPANCY_BUCKETS - 1)] += 1;
+        }
+        histogram
+    }
+
+    // Pages holding a single object which is old or uncollectible
+    pub fn pinned_by_single_object(&self) -> Vec<&HeapPage<'a>> {
+        self.pages.iter().filter(|page| page.objects.len() == 1 && page.is_pinned()).collect()
+    }
+
+    // Pages a perfect compaction would free: everything beyond the minimum number
+    // of pages needed for the live slots, but never a page holding an uncollectible object
+    pub fn reclaimable_pages(&self) -> usize {
+        let needed = self.live_slots().div_ceil(SLOTS_PER_PAGE);
+        let immovable = self.pages.iter().filter(|page| page.objects.iter().any(|node| node.is_uncollectible())).count();
+        self.pages.len().saturating_sub(needed.max(immovable))
+    }
+
+    pub fn write(&self, out: &mut dyn Write, limit: usize) -> io::Result<()> {
+        let live_slots = self.live_slots();
+        let total_slots = self.pages.len() * SLOTS_PER_PAGE;
+        let reclaimable = self.reclaimable_pages();
+
+        writeln!(out, "Pages: {} ({} bytes)", self.pages.len(), self.pages.len() as u64 * PAGE_SIZE)?;
+        writeln!(out, "Live slots: {} of {} ({:.1}% occupancy)", live_slots, total_slots, Self::percent(live_slots, total_slots))?;
+        writeln!(out, "Free slots: {} ({} bytes)", total_slots - live_slots, (total_slots - live_slots) as u64 * SLOT_SIZE)?;
+        writeln!(out, "Reclaimable by compaction: {} pages ({} bytes)", reclaimable, reclaimable as u64 * PAGE_SIZE)?;
+
+        writeln!(out, "\nPage occupancy:")?;
+        for (bucket, count) in self.occupancy_histogram().iter().enumerate() {
+            let low = bucket * 100 / OCCUPANCY_BUCKETS;
+            let high = (bucket + 1) * 100 / OCCUPANCY_BUCKETS;
+            writeln!(out, "  {:>3}-{:<3}% {:>8} pages", low, high, count)?;
+        }
+
+        let pinned = self.pinned_by_single_object();
+        writeln!(out, "\nPages pinned by a single old or uncollectible object: {}", pinned.len())?;
+        for page in pinned.iter().take(limit) {
+            let node = page.objects[0];
+            writeln!(out, "  page 0x{:x}: 0x{:x} {} old={} uncollectible={} {}",
+                     page.address, node.address(), self.heap_dump.label(node), node.is_old(), node.is_uncollectible(),
+                     node.allocation_site().unwrap_or_default())?;
+        }
+        Ok(())
+    }
+
+    fn percent(part: usize, total: usize) -> f64 {
+        if total == 0 { 0.0 } else { part as f64 * 100.0 / total as f64 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn heap_dump() -> HeapDump {
+    let mut heap_dump = HeapDump::default();
+    // Two objects on the first page, a single old one on the second
+    heap_dump.add_line(String::from(r#"{"address":"0x7f0000000028", "type":"ARRAY", "length":0, "memsize":40}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0x7f0000000050", "type":"ARRAY", "length":0, "memsize":40}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0x7f0000004028", "type":"STRING", "class":"0x1", "memsize":40, "flags":{"old":true}}"#));
+    heap_dump
+  }
+
+  #[test]
+  fn it_buckets_objects_into_pages() {
+    let heap_dump = heap_dump();
+    let pages = HeapPages::new(&heap_dump);
+
+    assert_eq!(HeapPages::page_address(0x7f0000004028), 0x7f0000004000);
+    assert_eq!(pages.pages.len(), 2);
+    assert_eq!(pages.live_slots(), 3);
+    assert_eq!(pages.occupancy_histogram()[0], 2);
+  }
+
+  #[test]
+  fn it_finds_pinned_and_reclaimable_pages() {
+    let heap_dump = heap_dump();
+    let pages = HeapPages::new(&heap_dump);
+
+    let pinned = pages.pinned_by_single_object();
+    assert_eq!(pinned.len(), 1);
+    assert_eq!(pinned[0].address, 0x7f0000004000);
+    assert_eq!(pages.reclaimable_pages(), 1);
+  }
+}
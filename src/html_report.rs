@@ -0,0 +1,205 @@
+use std::io;
+use std::io::Write;
+use super::dominator_tree::DominatorTree;
+use super::heap_dump::HeapDump;
+use super::heap_graph::HeapGraph;
+use super::object_space_2_6_0::node::Node;
+use super::totals::{Total, Totals};
+
+pub enum Cell {
+    Text(String),
+    Number(usize),
+}
+
+pub struct Table {
+    title: String,
+    headers: Vec<&'static str>,
+    rows: Vec<Vec<Cell>>,
+}
+
+// Single self-contained HTML page summarizing a dump.
+// Styles and the table sorting script are inlined so the file works offline.
+pub struct HtmlReport {
+    title: String,
+    tables: Vec<Table>,
+}
+
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 2em; color: #222; }
+h2 { margin-top: 2em; }
+table { border-collapse: collapse; font-size: 0.9em; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.6em; text-align: left; }
+th { background: #eee; cursor: pointer; user-select: none; }
+td.number { text-align: right; font-family: monospace; }
+";
+
+const SCRIPT: &str = "
+document.querySelectorAll('th').forEach(function (th) {
+  th.addEventListener('click', function () {
+    var table = th.closest('table');
+    var column = Array.prototype.indexOf.call(th.parentNode.children, th);
+    var descending = th.dataset.order !== 'desc';
+    th.dataset.order = descending ? 'desc' : 'asc';
+    var rows = Array.prototype.slice.call(table.tBodies[0].rows);
+    rows.sort(function (a, b) {
+      var x = a.cells[column].dataset.sort || a.cells[column].textContent;
+      var y = b.cells[column].dataset.sort || b.cells[column].textContent;
+      var result = isNaN(x) || isNaN(y) ? x.localeCompare(y) : x - y;
+      return descending ? -result : result;
+    });
+    rows.forEach(function (row) { table.tBodies[0].appendChild(row); });
+  });
+});
+";
+
+impl HtmlReport {
+    pub fn new(title: &str, heap_dump: &HeapDump, limit: usize) -> Self {
+        let graph = HeapGraph::new(heap_dump);
+        let tree = DominatorTree::new(heap_dump, &graph);
+
+        HtmlReport {
+            title: String::from(title),
+            tables: vec![
+                Self::types(heap_dump),
+                Self::classes(heap_dump, limit),
+                Self::allocation_sites(heap_dump, limit),
+                Self::retainers(heap_dump, &graph, &tree, limit),
+                Self::duplicate_strings(heap_dump, limit),
+                Self::roots(heap_dump, &graph, &tree),
+            ]
+        }
+    }
+
+    fn types(heap_dump: &HeapDump) -> Table {
+        let totals = Totals::by(heap_dump, |node| Some(String::from(node.type_name())));
+        Self::totals_table("Objects by type", "Type", totals.by_memsize(usize::MAX))
+    }
+
+    fn classes(heap_dump: &HeapDump, limit: usize) -> Table {
+        let totals = Totals::by(heap_dump, |node| Some(heap_dump.label(node)));
+        Self::totals_table("Memsize by class", "Class", totals.by_memsize(limit))
+    }
+
+    fn allocation_sites(heap_dump: &HeapDump, limit: usize) -> Table {
+        let totals = Totals::by(heap_dump, |node| node.allocation_site());
+        Self::totals_table("Top allocation sites", "Site", totals.by_memsize(limit))
+    }
+
+    fn totals_table(title: &str, key_header: &'static str, totals: Vec<(&String, &Total)>) -> Table {
+        Table {
+            title: String::from(title),
+            headers: vec![key_header, "Count", "Memsize"],
+            rows: totals.into_iter()
+                .map(|(key, total)| vec![Cell::Text(key.clone()), Cell::Number(total.count), Cell::Number(total.memsize)])
+                .collect()
+        }
+    }
+
+    fn retainers(heap_dump: &HeapDump, graph: &HeapGraph, tree: &DominatorTree, limit: usize) -> Table {
+        let mut retainers: Vec<(usize, &Node)> = heap_dump.objects()
+            .filter_map(|node| graph.index_of(node.address()).map(|index| (tree.retained_size(index), node)))
+            .collect();
+        retainers.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.address().cmp(&b.1.address())));
+        retainers.truncate(limit);
+
+        Table {
+            title: String::from("Top retainers"),
+            headers: vec!["Address", "Class", "Memsize", "Retained", "Site"],
+            rows: retainers.into_iter().map(|(retained, node)| vec![
+                Cell::Text(format!("0x{:x}", node.address())),
+                Cell::Text(heap_dump.label(node)),
+                Cell::Number(node.memsize()),
+                Cell::Number(retained),
+                Cell::Text(node.allocation_site().unwrap_or_default()),
+            ]).collect()
+        }
+    }
+
+    fn duplicate_strings(heap_dump: &HeapDump, limit: usize) -> Table {
+        let totals = Totals::by(heap_dump, |node| match node {
+            Node::String(node_string) => node_string.value.clone(),
+            _ => None
+        });
+        Table {
+            title: String::from("Duplicate strings"),
+            headers: vec!["Value", "Copies", "Memsize"],
+            rows: totals.by_count(limit).into_iter()
+                .filter(|(_, total)| total.count > 1)
+                .map(|(value, total)| vec![Cell::Text(value.clone()), Cell::Number(total.count), Cell::Number(total.memsize)])
+                .collect()
+        }
+    }
+
+    fn roots(heap_dump: &HeapDump, graph: &HeapGraph, tree: &DominatorTree) -> Table {
+        Table {
+            title: String::from("Roots"),
+            headers: vec!["Root", "References", "Retained"],
+            rows: heap_dump.roots().into_iter().map(|root| vec![
+                Cell::Text(root.root.clone()),
+                Cell::Number(root.references.len()),
+                Cell::Number(graph.root_index(&root.root).map(|index| tree.retained_size(index)).unwrap_or(0)),
+            ]).collect()
+        }
+    }
+
+    pub fn escape(text: &str) -> String {
+        text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+    }
+
+    pub fn write(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "<!DOCTYPE html>")?;
+        writeln!(out, "<html><head><meta charset=\"utf-8\"><title>{}</title>", Self::escape(&self.title))?;
+        writeln!(out, "<style>{}</style></head><body>", STYLE)?;
+        writeln!(out, "<h1>{}</h1>", Self::escape(&self.title))?;
+        for table in &self.tables {
+            self.write_table(out, table)?;
+        }
+        writeln!(out, "<script>{}</script></body></html>", SCRIPT)
+    }
+
+    fn write_table(&self, out: &mut dyn Write, table: &Table) -> io::Result<()> {
+        writeln!(out, "<h2>{}</h2>", Self::escape(&table.title))?;
+        if table.rows.is_empty() {
+            return writeln!(out, "<p>None</p>");
+        }
+        write!(out, "<table><thead><tr>")?;
+        for header in &table.headers {
+            write!(out, "<th>{}</th>", Self::escape(header))?;
+        }
+        writeln!(out, "</tr></thead><tbody>")?;
+        for row in &table.rows {
+            write!(out, "<tr>")?;
+            for cell in row {
+                match cell {
+                    Cell::Text(text) => write!(out, "<td>{}</td>", Self::escape(text))?,
+                    Cell::Number(number) => write!(out, "<td class=\"number\" data-sort=\"{}\">{}</td>", number, number)?,
+                }
+            }
+            writeln!(out, "</tr>")?;
+        }
+        writeln!(out, "</tbody></table>")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_writes_a_self_contained_page() {
+    let mut heap_dump = HeapDump::default();
+    heap_dump.add_line(String::from(r#"{"type":"ROOT", "root":"vm", "references":["0xa"]}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xa", "type":"ARRAY", "length":2, "references":["0xb", "0xc"], "memsize":10}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xb", "type":"STRING", "class":"0xd", "value":"<dup>", "memsize":40, "flags":{}}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xc", "type":"STRING", "class":"0xd", "value":"<dup>", "memsize":40, "flags":{}}"#));
+
+    let mut out = vec![];
+    HtmlReport::new("dump.json", &heap_dump, 10).write(&mut out).unwrap();
+    let html = String::from_utf8(out).unwrap();
+
+    assert!(html.contains("<td>&lt;dup&gt;</td><td class=\"number\" data-sort=\"2\">2</td>"));
+    assert!(html.contains("<td>vm</td><td class=\"number\" data-sort=\"1\">1</td><td class=\"number\" data-sort=\"90\">90</td>"));
+    assert!(!html.contains("src="));
+    assert!(!html.contains("href="));
+  }
+}
@@ -0,0 +1,334 @@
+use std::io;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+use std::time::Duration;
+use serde_json::{json, Value};
+use super::deserialize_utils::DeserializeUtils;
+use super::dominator_tree::DominatorTree;
+use super::heap_address::HeapAddress;
+use super::heap_dump::HeapDump;
+use super::heap_graph::{HeapGraph, RootPaths, Vertex};
+use super::object_space_2_6_0::node::Node;
+use super::query::Query;
+use super::totals::{Total, Totals};
+
+const DEFAULT_LIMIT: usize = 100;
+// Connections served at once; further ones wait in the accept queue
+const WORKERS: usize = 8;
+// So that an idle client can't hold a worker forever
+const IO_TIMEOUT: Duration = Duration::from_secs(10);
+
+const INDEX_HTML: &str = r#"<!DOCTYPE html>
+<html><head><meta charset="utf-8"><title>osn</title>
+<style>body { font-family: sans-serif; margin: 2em; } input { width: 30em; } pre { background: #f4f4f4; padding: 1em; }</style>
+</head><body>
+<h1>Object space navigator</h1>
+<p><input id="target" placeholder="/classes, /sites, /objects/0x7fc969077fa8, /paths/0x7fc969077fa8, /query?q=type:STRING"> <button id="go">Go</button></p>
+<pre id="result"></pre>
+<script>
+function go() {
+  var target = document.getElementById('target').value || '/classes';
+  fetch(target).then(function (response) { return response.json(); }).then(function (json) {
+    document.getElementById('result').textContent = JSON.stringify(json, null, 2);
+  });
+}
+document.getElementById('go').addEventListener('click', go);
+document.getElementById('target').addEventListener('keydown', function (event) { if (event.key === 'Enter') go(); });
+</script>
+</body></html>
+"#;
+
+pub struct Response {
+    pub status: &'static str,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl Response {
+    fn json(value: Value) -> Self {
+        Response { status: "200 OK", content_type: "application/json", body: value.to_string() }
+    }
+
+    fn error(status: &'static str, message: &str) -> Self {
+        Response { status, content_type: "application/json", body: json!({ "error": message }).to_string() }
+    }
+}
+
+// Read-only JSON API over a dump loaded once in memory.
+// Indexes are built up front so requests only do lookups.
+pub struct HttpServer<'a> {
+    heap_dump: &'a HeapDump,
+    graph: HeapGraph,
+    tree: DominatorTree,
+    paths: RootPaths,
+    predecessors: Vec<Vec<usize>>,
+    classes: Totals,
+    sites: Totals,
+}
+
+impl<'a> HttpServer<'a> {
+    pub fn new(heap_dump: &'a HeapDump) -> Self {
+        let graph = HeapGraph::new(heap_dump);
+        let tree = DominatorTree::new(heap_dump, &graph);
+        let paths = RootPaths::new(&graph);
+        let predecessors = graph.predecessors();
+
+        HttpServer {
+            heap_dump,
+            graph,
+            tree,
+            paths,
+            predecessors,
+            classes: Totals::by(heap_dump, |node| Some(heap_dump.label(node))),
+            sites: Totals::by(heap_dump, |node| node.allocation_site()),
+        }
+    }
+
+    pub fn serve(&self, bind: &str) -> io::Result<()> {
+        let listener = TcpListener::bind(bind)?;
+        eprintln!("Listening on http://{}", bind);
+
+        let (sender, receiver) = mpsc::sync_channel::<TcpStream>(WORKERS);
+        let receiver = Mutex::new(receiver);
+        thread::scope(|scope| {
+            for _ in 0..WORKERS {
+                scope.spawn(|| loop {
+                    let stream = match receiver.lock().unwrap().recv() {
+                        Ok(stream) => stream,
+                        Err(_) => break
+                    };
+                    if let Err(error) = self.handle(stream) {
+                        eprintln!("Request failure ({:?})", error);
+                    }
+                });
+            }
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => if sender.send(stream).is_err() { break; },
+                    Err(error) => eprintln!("Connection failure ({:?})", error)
+                }
+            }
+            drop(sender);
+        });
+        Ok(())
+    }
+
+    fn handle(&self, stream: TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(Some(IO_TIMEOUT))?;
+        stream.set_write_timeout(Some(IO_TIMEOUT))?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+
+        // Headers are not needed, but have to be consumed before responding
+        let mut header = String::new();
+        while reader.read_line(&mut header)? > 0 && header.trim() != "" { header.clear(); }
+
+        let mut parts = request_line.split_whitespace();
+        let response = match (parts.next(), parts.next()) {
+            (Some("GET"), Some(target)) => self.route(target),
+            _ => Response::error("405 Method Not Allowed", "Only GET requests are supported")
+        };
+
+        let mut stream = stream;
+        write!(stream, "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+               response.status, response.content_type, response.body.len())?;
+        stream.write_all(response.body.as_bytes())?;
+        stream.flush()
+    }
+
+    pub fn route(&self, target: &str) -> Response {
+        let (path, query_string) = match target.find('?') {
+            Some(split_at) => (&target[..split_at], &target[split_at + 1..]),
+            None => (target, "")
+        };
+        let params = Self::params(query_string);
+        let param = |name: &str| params.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str());
+        let limit = param("limit").and_then(|limit| limit.parse().ok()).unwrap_or(DEFAULT_LIMIT);
+        let segments: Vec<&str> = path.split('/').filter(|segment| !segment.is_empty()).collect();
+
+        match segments.as_slice() {
+            [] => Response { status: "200 OK", content_type: "text/html; charset=utf-8", body: String::from(INDEX_HTML) },
+            ["classes"] => Response::json(Self::totals_json("class", self.classes.by_memsize(limit))),
+            ["sites"] => Response::json(Self::totals_json("site", self.sites.by_memsize(limit))),
+            ["objects", address] => self.with_object(address, |node| self.object_json(node)),
+            ["objects", address, "referrers"] => self.with_object(address, |node| self.referrers_json(node)),
+            ["paths", address] => self.with_object(address, |node| self.path_json(node)),
+            ["query"] => match Query::parse(param("q").unwrap_or("")) {
+                Ok(query) => Response::json(self.query_json(&query, limit)),
+                Err(message) => Response::error("400 Bad Request", &message)
+            },
+            _ => Response::error("404 Not Found", "Unknown endpoint")
+        }
+    }
+
+    fn with_object<F>(&self, address: &str, render: F) -> Response
+    where
+        F: Fn(&Node) -> Value,
+    {
+        match DeserializeUtils::hex_to_heap_address(String::from(address)) {
+            Some(address) => match self.heap_dump.get(address) {
+                Some(node) => Response::json(render(node)),
+                None => Response::error("404 Not Found", "No object at this address")
+            },
+            None => Response::error("400 Bad Request", "Addresses are hex numbers, e.g. 0x7fc969077fa8")
+        }
+    }
+
+    fn object_json(&self, node: &Node) -> Value {
+        let retained_size = self.graph.index_of(node.address()).map(|index| self.tree.retained_size(index));
+        json!({
+            "address": Self::hex(node.address()),
+            "type": node.type_name(),
+            "class": node.class().map(Self::hex),
+            "class_name": self.heap_dump.label(node),
+            "memsize": node.memsize(),
+            "retained_size": retained_size,
+            "references": node.references().iter().map(|address| Self::hex(*address)).collect::<Vec<String>>(),
+            "file": node.file(),
+            "line": node.line(),
+            "method": node.method(),
+            "generation": node.generation(),
+            "extra": node.extra(),
+        })
+    }
+
+    fn vertex_json(&self, index: usize) -> Value {
+        match self.graph.vertex(index) {
+            Vertex::Root(name) => json!({ "root": name }),
+            Vertex::Object(address) => match self.heap_dump.get(*address) {
+                Some(node) => self.object_json(node),
+                None => json!({ "address": Self::hex(*address) })
+            },
+            Vertex::SuperRoot => Value::Null
+        }
+    }
+
+    fn referrers_json(&self, node: &Node) -> Value {
+        let referrers: Vec<Value> = match self.graph.index_of(node.address()) {
+            Some(index) => self.predecessors[index].iter().map(|referrer| self.vertex_json(*referrer)).collect(),
+            None => vec![]
+        };
+        json!({ "address": Self::hex(node.address()), "referrers": referrers })
+    }
+
+    fn path_json(&self, node: &Node) -> Value {
+        let path = self.graph.index_of(node.address())
+            .and_then(|index| self.paths.path(index))
+            .map(|path| path.into_iter().map(|index| self.vertex_json(index)).collect::<Vec<Value>>());
+        json!({ "address": Self::hex(node.address()), "path": path })
+    }
+
+    fn query_json(&self, query: &Query, limit: usize) -> Value {
+        let mut matches: Vec<&Node> = self.heap_dump.objects().filter(|node| query.matches(self.heap_dump, node)).collect();
+        matches.sort_by_key(|node| node.address());
+        let objects: Vec<Value> = matches.iter().take(limit).map(|node| self.object_json(node)).collect();
+        json!({ "count": matches.len(), "objects": objects })
+    }
+
+    fn totals_json(key: &str, totals: Vec<(&String, &Total)>) -> Value {
+        Value::Array(totals.into_iter().map(|(name, total)| json!({
+            key: name,
+            "count": total.count,
+            "memsize": total.memsize,
+        })).collect())
+    }
+
+    fn hex(address: HeapAddress) -> String {
+        format!("0x{:x}", address)
+    }
+
+    fn params(query_string: &str) -> Vec<(String, String)> {
+        query_string.split('&').filter(|pair| !pair.is_empty()).map(|pair| match pair.find('=') {
+            Some(split_at) => (Self::url_decode(&pair[..split_at]), Self::url_decode(&pair[split_at + 1..])),
+            None => (Self::url_decode(pair), String::new())
+        }).collect()
+    }
+
+    fn url_decode(value: &str) -> String {
+        let bytes = value.as_bytes();
+        let mut decoded = Vec::with_capacity(bytes.len());
+        let mut position = 0;
+        while position < bytes.len() {
+            match bytes[position] {
+                b'+' => decoded.push(b' '),
+                b'%' if position + 2 < bytes.len() => {
+                    let hex = String::from_utf8_lossy(&bytes[position + 1..position + 3]);
+                    match u8::from_str_radix(&hex, 16) {
+                        Ok(byte) => { decoded.push(byte); position += 2; }
+                        Err(_) => decoded.push(b'%')
+                    }
+                }
+                byte => decoded.push(byte)
+            }
+            position += 1;
+        }
+        String::from_utf8_lossy(&decoded).into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn heap_dump() -> HeapDump {
+    let mut heap_dump = HeapDump::default();
+    heap_dump.add_line(String::from(r#"{"type":"ROOT", "root":"vm", "references":["0xa"]}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xa", "type":"ARRAY", "length":1, "references":["0xb"], "memsize":10}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xb", "type":"STRING", "class":"0xc", "memsize":40, "file":"app.rb", "line":7, "flags":{}}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xc", "type":"CLASS", "name":"String", "memsize":0, "flags":{}}"#));
+    heap_dump
+  }
+
+  fn json(response: Response) -> Value {
+    assert_eq!(response.status, "200 OK");
+    serde_json::from_str(&response.body).unwrap()
+  }
+
+  #[test]
+  fn it_serves_objects() {
+    let heap_dump = heap_dump();
+    let server = HttpServer::new(&heap_dump);
+
+    let object = json(server.route("/objects/0xa"));
+    assert_eq!(object["type"], "ARRAY");
+    assert_eq!(object["retained_size"], 50);
+    assert_eq!(object["references"], json!(["0xb"]));
+
+    let referrers = json(server.route("/objects/0xb/referrers"));
+    assert_eq!(referrers["referrers"][0]["address"], "0xa");
+
+    let path = json(server.route("/paths/0xb"));
+    assert_eq!(path["path"], json!([{ "root": "vm" }, server.object_json(heap_dump.get(0xa).unwrap()), server.object_json(heap_dump.get(0xb).unwrap())]));
+  }
+
+  #[test]
+  fn it_serves_aggregates_and_queries() {
+    let heap_dump = heap_dump();
+    let server = HttpServer::new(&heap_dump);
+
+    let classes = json(server.route("/classes?limit=1"));
+    assert_eq!(classes, json!([{ "class": "String", "count": 1, "memsize": 40 }]));
+
+    let sites = json(server.route("/sites"));
+    assert_eq!(sites, json!([{ "site": "app.rb:7", "count": 1, "memsize": 40 }]));
+
+    let query = json(server.route("/query?q=type%3ASTRING+site:app.rb"));
+    assert_eq!(query["count"], 1);
+    assert_eq!(query["objects"][0]["address"], "0xb");
+  }
+
+  #[test]
+  fn it_reports_errors() {
+    let heap_dump = heap_dump();
+    let server = HttpServer::new(&heap_dump);
+
+    assert_eq!(server.route("/objects/0xff").status, "404 Not Found");
+    assert_eq!(server.route("/objects/nope").status, "400 Bad Request");
+    assert_eq!(server.route("/query?q=nope").status, "400 Bad Request");
+    assert_eq!(server.route("/nope").status, "404 Not Found");
+    assert_eq!(server.route("/").content_type, "text/html; charset=utf-8");
+  }
+}
@@ -0,0 +1,92 @@
+use std::io;
+use std::io::Write;
+use super::heap_dump::HeapDump;
+use super::object_space_2_6_0::imemo_type::ImemoType;
+use super::object_space_2_6_0::node::Node;
+use super::totals::Totals;
+
+// Internal memo objects by kind, with instruction sequences grouped by where
+// they were compiled, to tell memory taken by loaded code from application data
+pub struct ImemoReport {
+    by_type: Totals,
+    iseqs: Totals,
+    code_memsize: usize,
+    class_memsize: usize,
+    total_memsize: usize,
+}
+
+impl ImemoReport {
+    pub fn new(heap_dump: &HeapDump) -> Self {
+        let mut report = ImemoReport {
+            by_type: Totals::default(),
+            iseqs: Totals::default(),
+            code_memsize: 0,
+            class_memsize: 0,
+            total_memsize: 0,
+        };
+
+        for node in heap_dump.objects() {
+            report.total_memsize += node.memsize();
+            match node {
+                Node::Imemo(node_imemo) => {
+                    report.by_type.add(node_imemo.imemo_type.to_string(), node_imemo.memsize);
+                    if node_imemo.imemo_type.is_code() {
+                        report.code_memsize += node_imemo.memsize;
+                    }
+                    if node_imemo.imemo_type == ImemoType::Iseq {
+                        report.iseqs.add(Self::compiled_from(node), node_imemo.memsize);
+                    }
+                }
+                Node::Class(_) | Node::Module(_) | Node::Iclass(_) => report.class_memsize += node.memsize(),
+                _ => {}
+            }
+        }
+        report
+    }
+
+    // Allocation file and method, i.e. the code running when the iseq was compiled
+    fn compiled_from(node: &Node) -> String {
+        match (node.file(), node.method()) {
+            (Some(file), Some(method)) => format!("{} ({})", file, method),
+            (Some(file), None) => String::from(file),
+            _ => String::from("(unknown)")
+        }
+    }
+
+    pub fn write(&self, out: &mut dyn Write, limit: usize) -> io::Result<()> {
+        let data_memsize = self.total_memsize - self.code_memsize - self.class_memsize;
+        writeln!(out, "Code (iseq, ment, cref, ast, call caches): {:>12} bytes {:>5.1}%", self.code_memsize, self.percent(self.code_memsize))?;
+        writeln!(out, "Classes, modules and iclasses:             {:>12} bytes {:>5.1}%", self.class_memsize, self.percent(self.class_memsize))?;
+        writeln!(out, "Everything else:                           {:>12} bytes {:>5.1}%", data_memsize, self.percent(data_memsize))?;
+        writeln!(out)?;
+
+        Totals::write(out, "Imemo by kind", &self.by_type.by_memsize(usize::MAX))?;
+        Totals::write(out, "Instruction sequences by allocation file and method", &self.iseqs.by_memsize(limit))
+    }
+
+    fn percent(&self, memsize: usize) -> f64 {
+        if self.total_memsize == 0 { 0.0 } else { memsize as f64 * 100.0 / self.total_memsize as f64 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::totals::Total;
+
+  #[test]
+  fn it_splits_code_from_data() {
+    let mut heap_dump = HeapDump::default();
+    heap_dump.add_line(String::from(r#"{"address":"0xa", "type":"IMEMO", "imemo_type":"iseq", "memsize":300, "file":"app.rb", "method":"require", "flags":{}}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xb", "type":"IMEMO", "imemo_type":"env", "memsize":100, "flags":{}}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xc", "type":"CLASS", "name":"Foo", "memsize":500, "flags":{}}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xd", "type":"ARRAY", "length":0, "memsize":100}"#));
+
+    let report = ImemoReport::new(&heap_dump);
+    assert_eq!(report.code_memsize, 300);
+    assert_eq!(report.class_memsize, 500);
+    assert_eq!(report.total_memsize, 1000);
+    assert_eq!(report.by_type.by_memsize(10).len(), 2);
+    assert_eq!(report.iseqs.by_memsize(10), vec![(&String::from("app.rb (require)"), &Total { count: 1, memsize: 300 })]);
+  }
+}
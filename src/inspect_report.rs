@@ -0,0 +1,66 @@
+use std::io;
+use std::io::Write;
+use super::heap_address::HeapAddress;
+use super::heap_dump::HeapDump;
+use super::object_space_2_6_0::node::Node;
+
+// Everything the dump recorded about a single object, including fields the
+// typed structs don't know yet, as written by a newer Ruby.
+pub struct InspectReport<'a> {
+    heap_dump: &'a HeapDump,
+    node: &'a Node,
+}
+
+impl<'a> InspectReport<'a> {
+    pub fn new(heap_dump: &'a HeapDump, address: HeapAddress) -> Option<Self> {
+        heap_dump.get(address).map(|node| InspectReport { heap_dump, node })
+    }
+
+    pub fn write(&self, out: &mut dyn Write) -> io::Result<()> {
+        let node = self.node;
+        writeln!(out, "{} 0x{:x}", self.heap_dump.label(node), node.address())?;
+        writeln!(out, "  type: {}", node.type_name())?;
+        if let Some(class) = node.class() {
+            writeln!(out, "  class: 0x{:x}", class)?;
+        }
+        writeln!(out, "  memsize: {}", node.memsize())?;
+        if let Some(site) = node.allocation_site() {
+            writeln!(out, "  site: {}", site)?;
+        }
+        if let Some(method) = node.method() {
+            writeln!(out, "  method: {}", method)?;
+        }
+        if let Some(generation) = node.generation() {
+            writeln!(out, "  generation: {}", generation)?;
+        }
+        let references: Vec<String> = node.references().iter().map(|address| format!("0x{:x}", address)).collect();
+        writeln!(out, "  references: [{}]", references.join(", "))?;
+
+        if !node.extra().is_empty() {
+            writeln!(out, "Fields unknown to this version:")?;
+            for (field, value) in node.extra() {
+                writeln!(out, "  {}: {}", field, value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_lists_unknown_fields() {
+    let mut heap_dump = HeapDump::default();
+    heap_dump.add_line(String::from(r#"{"address":"0xa", "type":"ARRAY", "length":1, "references":["0xb"], "memsize":40, "shape_id":7, "slot_size":40}"#));
+
+    let mut out = vec![];
+    InspectReport::new(&heap_dump, 0xa).unwrap().write(&mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.starts_with("ARRAY 0xa\n"));
+    assert!(out.contains("  references: [0xb]\n"));
+    assert!(out.ends_with("Fields unknown to this version:\n  shape_id: 7\n  slot_size: 40\n"));
+    assert!(InspectReport::new(&heap_dump, 0xb).is_none());
+  }
+}
@@ -0,0 +1,121 @@
+use std::collections::{BTreeMap, HashSet};
+use std::io;
+use std::io::Write;
+use super::heap_address::HeapAddress;
+use super::heap_dump::HeapDump;
+use super::heap_graph::{HeapGraph, RootPaths};
+use super::totals::Totals;
+
+// Missing addresses listed per referring type
+const EXAMPLE_LIMIT: usize = 3;
+
+// Consistency of a dump: garbage no root reaches (dumps taken without a full GC
+// first), references to addresses with no record, and addresses recorded twice.
+pub struct IntegrityReport {
+    objects: usize,
+    garbage: Totals,
+    // Referring type, or ROOT category, to the count of dangling references and a few of their targets
+    missing: BTreeMap<String, (usize, Vec<HeapAddress>)>,
+    duplicates: Vec<HeapAddress>,
+}
+
+impl IntegrityReport {
+    pub fn new(heap_dump: &HeapDump) -> Self {
+        let graph = HeapGraph::new(heap_dump);
+        let paths = RootPaths::new(&graph);
+
+        let mut garbage = Totals::default();
+        let mut missing: BTreeMap<String, (usize, Vec<HeapAddress>)> = BTreeMap::new();
+        let mut add_missing = |referrer: String, address: HeapAddress| {
+            let (count, examples) = missing.entry(referrer).or_default();
+            *count += 1;
+            if examples.len() < EXAMPLE_LIMIT { examples.push(address); }
+        };
+
+        for root in heap_dump.roots() {
+            for reference in root.references.iter().filter(|reference| heap_dump.get(**reference).is_none()) {
+                add_missing(format!("ROOT {}", root.root), *reference);
+            }
+        }
+        let mut objects = 0;
+        for node in heap_dump.objects() {
+            objects += 1;
+            if !graph.index_of(node.address()).map(|index| paths.is_reachable(index)).unwrap_or(false) {
+                garbage.add(heap_dump.label(node), node.memsize());
+            }
+            for reference in node.references().iter().filter(|reference| heap_dump.get(**reference).is_none()) {
+                add_missing(String::from(node.type_name()), *reference);
+            }
+        }
+
+        let duplicates: HashSet<HeapAddress> = heap_dump.duplicates().iter().cloned().collect();
+        let mut duplicates: Vec<HeapAddress> = duplicates.into_iter().collect();
+        duplicates.sort_unstable();
+        IntegrityReport { objects, garbage, missing, duplicates }
+    }
+
+    pub fn garbage(&self) -> (usize, usize) {
+        let totals = self.garbage.by_count(usize::MAX);
+        (totals.iter().map(|(_, total)| total.count).sum(), totals.iter().map(|(_, total)| total.memsize).sum())
+    }
+
+    pub fn write(&self, out: &mut dyn Write, limit: usize) -> io::Result<()> {
+        let (garbage_count, garbage_memsize) = self.garbage();
+        writeln!(out, "Objects: {}", self.objects)?;
+        writeln!(out, "Unreachable from any root: {} objects, {} bytes", garbage_count, garbage_memsize)?;
+        writeln!(out, "References to missing addresses: {}", self.missing.values().map(|(count, _)| count).sum::<usize>())?;
+        writeln!(out, "Duplicate address records: {}", self.duplicates.len())?;
+        writeln!(out)?;
+
+        Totals::write(out, "Unreachable objects by class (drop them with --drop-garbage)", &self.garbage.by_memsize(limit))?;
+
+        writeln!(out, "References to missing addresses by referring type:")?;
+        if self.missing.is_empty() {
+            writeln!(out, "  none")?;
+        }
+        for (referrer, (count, examples)) in &self.missing {
+            let examples: Vec<String> = examples.iter().map(|address| format!("0x{:x}", address)).collect();
+            writeln!(out, "  {:>8} references  {} (e.g. {})", count, referrer, examples.join(", "))?;
+        }
+        writeln!(out)?;
+
+        writeln!(out, "Duplicate address records:")?;
+        if self.duplicates.is_empty() {
+            writeln!(out, "  none")?;
+        }
+        for address in self.duplicates.iter().take(limit) {
+            writeln!(out, "  0x{:x}", address)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn heap_dump() -> HeapDump {
+    let mut heap_dump = HeapDump::default();
+    heap_dump.add_line(String::from(r#"{"type":"ROOT", "root":"vm", "references":["0xa", "0xf"]}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xa", "type":"ARRAY", "length":1, "references":["0xe"], "memsize":40}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xb", "type":"ARRAY", "length":0, "memsize":100}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xb", "type":"ARRAY", "length":0, "memsize":100}"#));
+    heap_dump
+  }
+
+  #[test]
+  fn it_finds_integrity_problems() {
+    let report = IntegrityReport::new(&heap_dump());
+    assert_eq!(report.garbage(), (1, 100));
+    assert_eq!(report.missing.get("ROOT vm"), Some(&(1, vec![0xf])));
+    assert_eq!(report.missing.get("ARRAY"), Some(&(1, vec![0xe])));
+    assert_eq!(report.duplicates, vec![0xb]);
+  }
+
+  #[test]
+  fn it_drops_garbage() {
+    let mut heap_dump = heap_dump();
+    assert_eq!(heap_dump.drop_unreachable(), (1, 100));
+    assert_eq!(IntegrityReport::new(&heap_dump).garbage(), (0, 0));
+  }
+}
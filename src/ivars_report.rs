@@ -0,0 +1,98 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::io::Write;
+use super::heap_dump::HeapDump;
+use super::heap_pages::SLOT_SIZE;
+use super::object_space_2_6_0::node::Node;
+use super::totals::Totals;
+
+// Instance variable counts of plain objects per class. Ruby 2.6 embeds up to
+// three ivars in the object slot, more move to a separately allocated table.
+// Instances of one class with varying ivar counts usually set ivars lazily or
+// conditionally, which defeats the ivar index caches.
+pub struct IvarsReport {
+    histograms: HashMap<String, BTreeMap<usize, usize>>,
+    extended: Totals,
+    structs: Totals,
+}
+
+impl IvarsReport {
+    pub fn new(heap_dump: &HeapDump) -> Self {
+        let mut report = IvarsReport { histograms: HashMap::new(), extended: Totals::default(), structs: Totals::default() };
+        for node in heap_dump.objects() {
+            match node {
+                Node::Object(node_object) => {
+                    let label = heap_dump.label(node);
+                    if node_object.memsize as u64 > SLOT_SIZE {
+                        report.extended.add(label.clone(), node_object.memsize - SLOT_SIZE as usize);
+                    }
+                    *report.histograms.entry(label).or_default().entry(node_object.ivars).or_insert(0) += 1;
+                }
+                Node::Struct(node_struct) => report.structs.add(heap_dump.label(node), node_struct.memsize),
+                _ => {}
+            }
+        }
+        report
+    }
+
+    // Classes whose instances don't all have the same number of ivars, most varied first
+    pub fn inconsistent(&self) -> Vec<(&String, &BTreeMap<usize, usize>)> {
+        let mut inconsistent: Vec<(&String, &BTreeMap<usize, usize>)> = self.histograms.iter()
+            .filter(|(_, histogram)| histogram.len() > 1)
+            .collect();
+        inconsistent.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then(Self::count(b.1).cmp(&Self::count(a.1))).then(a.0.cmp(b.0)));
+        inconsistent
+    }
+
+    fn count(histogram: &BTreeMap<usize, usize>) -> usize {
+        histogram.values().sum()
+    }
+
+    // "0 ivars x 2, 3 ivars x 10"
+    fn describe(histogram: &BTreeMap<usize, usize>) -> String {
+        histogram.iter().map(|(ivars, count)| format!("{} ivars x {}", ivars, count)).collect::<Vec<String>>().join(", ")
+    }
+
+    pub fn write(&self, out: &mut dyn Write, limit: usize) -> io::Result<()> {
+        let mut histograms: Vec<(&String, &BTreeMap<usize, usize>)> = self.histograms.iter().collect();
+        histograms.sort_by(|a, b| Self::count(b.1).cmp(&Self::count(a.1)).then(a.0.cmp(b.0)));
+        writeln!(out, "Ivar counts by class:")?;
+        for (class, histogram) in histograms.into_iter().take(limit) {
+            writeln!(out, "  {:>8} objects  {}: {}", Self::count(histogram), class, Self::describe(histogram))?;
+        }
+        writeln!(out)?;
+
+        let inconsistent = self.inconsistent();
+        writeln!(out, "Classes with inconsistent ivar counts: {}", inconsistent.len())?;
+        for (class, histogram) in inconsistent.into_iter().take(limit) {
+            writeln!(out, "  {:>8} objects  {}: {}", Self::count(histogram), class, Self::describe(histogram))?;
+        }
+        writeln!(out)?;
+
+        Totals::write(out, "Objects with an extended ivar table by class (bytes beyond the slot)", &self.extended.by_memsize(limit))?;
+        Totals::write(out, "Struct instances by class", &self.structs.by_memsize(limit))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::totals::Total;
+
+  #[test]
+  fn it_groups_ivar_counts() {
+    let mut heap_dump = HeapDump::default();
+    heap_dump.add_line(String::from(r#"{"address":"0x1", "type":"CLASS", "name":"User", "memsize":0, "flags":{}}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0x2", "type":"CLASS", "name":"Point", "memsize":0, "flags":{}}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xa", "type":"OBJECT", "class":"0x1", "ivars":2, "memsize":40, "flags":{}}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xb", "type":"OBJECT", "class":"0x1", "ivars":5, "memsize":80, "flags":{}}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xc", "type":"STRUCT", "class":"0x2", "memsize":40, "flags":{}}"#));
+
+    let report = IvarsReport::new(&heap_dump);
+    let inconsistent = report.inconsistent();
+    assert_eq!(inconsistent.len(), 1);
+    assert_eq!(IvarsReport::describe(inconsistent[0].1), "2 ivars x 1, 5 ivars x 1");
+    assert_eq!(report.extended.by_memsize(10), vec![(&String::from("User"), &Total { count: 1, memsize: 40 })]);
+    assert_eq!(report.structs.by_memsize(10), vec![(&String::from("Point"), &Total { count: 1, memsize: 40 })]);
+  }
+}
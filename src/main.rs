@@ -1,34 +1,100 @@
 extern crate serde;
 extern crate serde_json;
+extern crate flate2;
 #[macro_use]
 extern crate serde_derive;
 use std::env;
-use std::fs::File;
+use std::io;
 use std::process;
 
+mod anonymous_classes;
+mod class_hierarchy;
+mod cli_args;
+mod collections_report;
+mod commands;
+mod cycles_report;
+mod data_report;
+mod dominator_tree;
+mod dump_writer;
+mod extract;
+mod files_report;
+mod flame_graph;
+mod gc_flags;
 mod heap_dump;
+mod heap_graph;
+mod heap_pages;
+mod html_report;
+mod http_server;
+mod imemo_report;
+mod inspect_report;
+mod integrity_report;
+mod ivars_report;
 mod sys_check;
+mod numerics_report;
 mod object_space_2_6_0;
+mod pprof;
+mod procs_report;
+mod query;
+mod regexp_report;
+mod roots_report;
+mod strings_report;
+mod strongly_connected;
+mod symbols_report;
+mod totals;
+mod validator;
 pub mod deserialize_utils;
 pub mod heap_address;
+pub mod serialize_utils;
+
+use cli_args::CliArgs;
+use commands::Commands;
 
 fn main() {
-    let args: Vec<_> = env::args().collect();
-    let first_arg = args.get(1);
+    let args = CliArgs::parse(env::args().skip(1).collect());
 
-    if first_arg.is_none() {
-        println!("Usage: osn [ruby object space dump]");
-        process::exit(1);
+    if args.switch("-h") || args.switch("--help") {
+        println!("{}", commands::USAGE);
+        return;
     }
-    let filename = first_arg.unwrap();
-    let file = File::open(filename);
 
-    if file.is_err() {
-        println!("File '{}' read failure ({:?})", filename, file);
-        process::exit(1);
+    match args.positional(0) {
+        None => {
+            println!("{}", commands::USAGE);
+            process::exit(1);
+        }
+        Some("flame") => Commands::flame(&args),
+        Some("report") => Commands::report(&args),
+        Some("serve") => Commands::serve(&args),
+        Some("export") => Commands::export(&args),
+        Some("pages") => Commands::pages(&args),
+        Some("gc-flags") => Commands::gc_flags(&args),
+        Some("imemo") => Commands::imemo(&args),
+        Some("ancestors") => Commands::ancestors(&args),
+        Some("subclasses") => Commands::subclasses(&args),
+        Some("iclasses") => Commands::iclasses(&args),
+        Some("anon-classes") => Commands::anon_classes(&args),
+        Some("collections") => Commands::collections(&args),
+        Some("strings") => Commands::strings(&args),
+        Some("files") => Commands::files(&args),
+        Some("data") => Commands::data(&args),
+        Some("procs") => Commands::procs(&args),
+        Some("symbols") => Commands::symbols(&args),
+        Some("regexps") => Commands::regexps(&args),
+        Some("numerics") => Commands::numerics(&args),
+        Some("ivars") => Commands::ivars(&args),
+        Some("roots") => Commands::roots(&args),
+        Some("cycles") => Commands::cycles(&args),
+        Some("integrity") => Commands::integrity(&args),
+        Some("validate") => Commands::validate(&args),
+        Some("inspect") => Commands::inspect(&args),
+        Some("rewrite") => Commands::rewrite(&args),
+        Some("extract") => Commands::extract(&args),
+        Some(filename) => print_roots(&args, filename)
     }
+}
 
-    let hd = heap_dump::HeapDump::load_file(file.unwrap());
+fn print_roots(args: &CliArgs, filename: &str) {
+    let hd = Commands::load_dump_logging(args, Some(filename), &mut io::stdout());
     hd.print_roots();
 
     let fsize = sys_check::FileCheck::size_kb(filename);
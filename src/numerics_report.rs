@@ -0,0 +1,70 @@
+use std::io;
+use std::io::Write;
+use super::heap_dump::HeapDump;
+use super::object_space_2_6_0::float_value::FloatValue;
+use super::object_space_2_6_0::node::Node;
+use super::totals::Totals;
+
+// Numbers that needed a heap slot: floats outside the flonum range, bignums,
+// rationals and complexes. Sites allocating many of them are usually arithmetic
+// in a loop, and each of those objects is an avoidable allocation.
+// Only floats come with a value, 2.6 dumps nothing but the slot for the others.
+pub struct NumericsReport {
+    by_site: Totals,
+    floats: Totals,
+}
+
+impl NumericsReport {
+    pub fn new(heap_dump: &HeapDump) -> Self {
+        let mut report = NumericsReport { by_site: Totals::default(), floats: Totals::default() };
+        for node in heap_dump.objects() {
+            match node {
+                Node::Float(_) | Node::Bignum(_) | Node::Rational(_) | Node::Complex(_) => {}
+                _ => continue
+            }
+            let site = node.allocation_site().unwrap_or_else(|| String::from("(unknown)"));
+            report.by_site.add(format!("{} {}", node.type_name(), site), node.memsize());
+            if let Node::Float(node_float) = node {
+                report.floats.add(String::from(Self::float_kind(node_float.value)), node_float.memsize);
+            }
+        }
+        report
+    }
+
+    // Why a float ended up on the heap
+    pub fn float_kind(value: FloatValue) -> &'static str {
+        if value.is_flonum() { "within flonum range (32 bit build?)" }
+        else if value.0.is_nan() { "nan" }
+        else if value.0.is_infinite() { "infinity" }
+        else if value.0 == 0.0 { "negative zero" }
+        else if value.0.abs() >= 1.0 { "too large for a flonum" }
+        else { "too small for a flonum" }
+    }
+
+    pub fn write(&self, out: &mut dyn Write, limit: usize) -> io::Result<()> {
+        Totals::write(out, "Heap allocated numerics by type and allocation site", &self.by_site.by_count(limit))?;
+        Totals::write(out, "Heap allocated floats by reason", &self.floats.by_count(usize::MAX))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::totals::Total;
+
+  #[test]
+  fn it_groups_numerics_by_site() {
+    let mut heap_dump = HeapDump::default();
+    heap_dump.add_line(String::from(r#"{"address":"0xa", "type":"FLOAT", "class":"0x1", "frozen":true, "value":"1e+100", "memsize":40, "file":"calc.rb", "line":5, "flags":{}}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xb", "type":"FLOAT", "class":"0x1", "frozen":true, "value":"nan", "memsize":40, "file":"calc.rb", "line":5, "flags":{}}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xc", "type":"BIGNUM", "class":"0x2", "frozen":true, "memsize":48, "file":"calc.rb", "line":9, "flags":{}}"#));
+
+    let report = NumericsReport::new(&heap_dump);
+    assert_eq!(report.by_site.by_count(10), vec![
+      (&String::from("FLOAT calc.rb:5"), &Total { count: 2, memsize: 80 }),
+      (&String::from("BIGNUM calc.rb:9"), &Total { count: 1, memsize: 48 }),
+    ]);
+    assert_eq!(report.floats.by_count(10).len(), 2);
+    assert_eq!(NumericsReport::float_kind(FloatValue(-1e-100)), "too small for a flonum");
+  }
+}
@@ -1,8 +1,14 @@
-#[derive(Hash, Eq, PartialEq, Debug, Deserialize)]
+// Listed in the order rb_obj_gc_flags reports them
+#[derive(Hash, Eq, PartialEq, Debug, Deserialize, Serialize)]
 pub struct Flags {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub wb_protected: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub old: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub uncollectible: Option<bool>,
-    pub marked: Option<bool>,
-    pub marking: Option<bool>
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub marking: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub marked: Option<bool>
 }
@@ -0,0 +1,132 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+// A FLOAT value, dumped with printf's %g: "1e+100", "nan", "-nan", "inf", "-inf".
+// Compared and hashed by bit pattern so that nodes can stay Eq and Hash.
+#[derive(Clone, Copy, Debug)]
+pub struct FloatValue(pub f64);
+
+impl FloatValue {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "nan" | "NaN" => Some(FloatValue(f64::NAN)),
+            "-nan" => Some(FloatValue(-f64::NAN)),
+            "inf" | "Infinity" => Some(FloatValue(f64::INFINITY)),
+            "-inf" | "-Infinity" => Some(FloatValue(f64::NEG_INFINITY)),
+            other => other.parse().ok().map(FloatValue)
+        }
+    }
+
+    // On 64 bit builds floats whose exponent fits in flonum's reduced range, and
+    // +0.0, are immediates. Anything else, nan and infinities included, needs a heap slot.
+    // 1.72723e-77 (bits 0x3000000000000000) is in range but left out, its flonum encodes +0.0.
+    pub fn is_flonum(&self) -> bool {
+        let bits = self.0.to_bits();
+        let exponent_bits = (bits >> 60) & 0x7;
+        bits == 0 || (bits != 0x3000_0000_0000_0000 && (exponent_bits == 3 || exponent_bits == 4))
+    }
+
+    fn trim_zeros(number: &str) -> &str {
+        if number.contains('.') { number.trim_end_matches('0').trim_end_matches('.') } else { number }
+    }
+}
+
+impl PartialEq for FloatValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.to_bits() == other.0.to_bits()
+    }
+}
+
+impl Eq for FloatValue {}
+
+impl Hash for FloatValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.to_bits().hash(state);
+    }
+}
+
+// Formatted like %g: 6 significant digits, exponent notation below 1e-4 and
+// from 1e6 on, trailing zeros dropped
+impl fmt::Display for FloatValue {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let value = self.0;
+        if value.is_nan() {
+            return formatter.write_str(if value.is_sign_negative() { "-nan" } else { "nan" });
+        }
+        if value.is_infinite() {
+            return formatter.write_str(if value > 0.0 { "inf" } else { "-inf" });
+        }
+        if value == 0.0 {
+            return formatter.write_str(if value.is_sign_negative() { "-0" } else { "0" });
+        }
+        let scientific = format!("{:.5e}", value);
+        let (mantissa, exponent) = scientific.split_at(scientific.find('e').unwrap());
+        let exponent: i32 = exponent[1..].parse().unwrap();
+        if !(-4..6).contains(&exponent) {
+            let sign = if exponent < 0 { '-' } else { '+' };
+            write!(formatter, "{}e{}{:02}", Self::trim_zeros(mantissa), sign, exponent.abs())
+        } else {
+            formatter.write_str(Self::trim_zeros(&format!("{:.*}", (5 - exponent) as usize, value)))
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for FloatValue {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value: String = Deserialize::deserialize(deserializer)?;
+        FloatValue::parse(&value).ok_or_else(|| serde::de::Error::custom(format!("invalid float value '{}'", value)))
+    }
+}
+
+impl Serialize for FloatValue {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_parses_special_values() {
+    assert!(FloatValue::parse("nan").unwrap().0.is_nan());
+    assert_eq!(FloatValue::parse("inf"), Some(FloatValue(f64::INFINITY)));
+    assert_eq!(FloatValue::parse("-inf"), Some(FloatValue(f64::NEG_INFINITY)));
+    assert_eq!(FloatValue::parse("1.79769e+308"), Some(FloatValue(1.79769e308)));
+    assert_eq!(FloatValue::parse("one"), None);
+  }
+
+  #[test]
+  fn it_tells_flonums() {
+    assert!(FloatValue(1.5).is_flonum());
+    assert!(FloatValue(0.0).is_flonum());
+    assert!(!FloatValue(-0.0).is_flonum());
+    assert!(!FloatValue(1e100).is_flonum());
+    assert!(!FloatValue(f64::NAN).is_flonum());
+  }
+
+  #[test]
+  fn it_excludes_the_float_encoded_like_zero() {
+    assert!(!FloatValue(f64::from_bits(0x3000_0000_0000_0000)).is_flonum());
+    assert!(FloatValue(f64::from_bits(0x3000_0000_0000_0001)).is_flonum());
+    assert_eq!(FloatValue(f64::from_bits(0x3000_0000_0000_0000)).to_string(), "1.72723e-77");
+  }
+
+  #[test]
+  fn it_formats_like_printf() {
+    let formatted: Vec<String> = [1e100, 1.79769e308, 0.1, 123456.0, 1234567.0, 0.0001, 0.00001234, -2.5, 100.0]
+      .iter().map(|value| FloatValue(*value).to_string()).collect();
+    assert_eq!(formatted, vec!["1e+100", "1.79769e+308", "0.1", "123456", "1.23457e+06", "0.0001", "1.234e-05", "-2.5", "100"]);
+    for special in &["nan", "-nan", "inf", "-inf"] {
+      assert_eq!(FloatValue::parse(special).unwrap().to_string(), *special);
+    }
+  }
+}
@@ -0,0 +1,115 @@
+use std::fmt;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+// Internal memo object kinds (enum imemo_type in internal.h).
+// Kinds introduced by later Rubies are kept verbatim as Unknown.
+#[derive(Hash, Eq, PartialEq, Clone, Debug)]
+pub enum ImemoType {
+    Env,
+    Cref,
+    Svar,
+    ThrowData,
+    Ifunc,
+    Memo,
+    Ment,
+    Iseq,
+    Tmpbuf,
+    Ast,
+    ParserStrterm,
+    Callinfo,
+    Callcache,
+    Constcache,
+    Unknown(String),
+}
+
+impl ImemoType {
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "env" => ImemoType::Env,
+            "cref" => ImemoType::Cref,
+            "svar" => ImemoType::Svar,
+            "throw_data" => ImemoType::ThrowData,
+            "ifunc" => ImemoType::Ifunc,
+            "memo" => ImemoType::Memo,
+            "ment" => ImemoType::Ment,
+            "iseq" => ImemoType::Iseq,
+            "tmpbuf" => ImemoType::Tmpbuf,
+            "ast" => ImemoType::Ast,
+            "parser_strterm" => ImemoType::ParserStrterm,
+            "callinfo" => ImemoType::Callinfo,
+            "callcache" => ImemoType::Callcache,
+            "constcache" => ImemoType::Constcache,
+            other => ImemoType::Unknown(String::from(other)),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            ImemoType::Env => "env",
+            ImemoType::Cref => "cref",
+            ImemoType::Svar => "svar",
+            ImemoType::ThrowData => "throw_data",
+            ImemoType::Ifunc => "ifunc",
+            ImemoType::Memo => "memo",
+            ImemoType::Ment => "ment",
+            ImemoType::Iseq => "iseq",
+            ImemoType::Tmpbuf => "tmpbuf",
+            ImemoType::Ast => "ast",
+            ImemoType::ParserStrterm => "parser_strterm",
+            ImemoType::Callinfo => "callinfo",
+            ImemoType::Callcache => "callcache",
+            ImemoType::Constcache => "constcache",
+            ImemoType::Unknown(name) => name,
+        }
+    }
+
+    // Compiled code and method/constant lookup structures, as opposed to runtime data
+    pub fn is_code(&self) -> bool {
+        matches!(self, ImemoType::Iseq | ImemoType::Ment | ImemoType::Cref | ImemoType::Ast
+                 | ImemoType::Callinfo | ImemoType::Callcache | ImemoType::Constcache)
+    }
+}
+
+impl fmt::Display for ImemoType {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for ImemoType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let name: String = Deserialize::deserialize(deserializer)?;
+        Ok(ImemoType::from_name(&name))
+    }
+}
+
+impl Serialize for ImemoType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_deserializes_known_types() {
+    let imemo_type: ImemoType = serde_json::from_str(r#""throw_data""#).unwrap();
+    assert_eq!(imemo_type, ImemoType::ThrowData);
+    assert_eq!(imemo_type.name(), "throw_data");
+  }
+
+  #[test]
+  fn it_keeps_unknown_types() {
+    let imemo_type: ImemoType = serde_json::from_str(r#""shape""#).unwrap();
+    assert_eq!(imemo_type, ImemoType::Unknown(String::from("shape")));
+    assert_eq!(imemo_type.to_string(), "shape");
+  }
+}
@@ -18,3 +18,6 @@ pub mod node_rational;
 pub mod node_struct;
 pub mod node_match;
 pub mod flags;
+pub mod float_value;
+pub mod imemo_type;
+pub mod node;
@@ -0,0 +1,378 @@
+use std::collections::BTreeMap;
+use serde::{Serialize, Serializer};
+use serde_json::{Error, Value};
+use crate::deserialize_utils::DeserializeUtils;
+use crate::heap_address::HeapAddress;
+use crate::serialize_utils::SerializeUtils;
+use super::flags::Flags;
+use super::node_root::NodeRoot;
+use super::node_array::NodeArray;
+use super::node_string::NodeString;
+use super::node_imemo::NodeImemo;
+use super::node_object::NodeObject;
+use super::node_regexp::NodeRegexp;
+use super::node_class::NodeClass;
+use super::node_module::NodeModule;
+use super::node_hash::NodeHash;
+use super::node_data::NodeData;
+use super::node_iclass::NodeIclass;
+use super::node_symbol::NodeSymbol;
+use super::node_complex::NodeComplex;
+use super::node_bignum::NodeBignum;
+use super::node_file::NodeFile;
+use super::node_float::NodeFloat;
+use super::node_rational::NodeRational;
+use super::node_struct::NodeStruct;
+use super::node_match::NodeMatch;
+
+// Single dump line, dispatched on its "type" field
+#[derive(PartialEq, Debug, Deserialize)]
+#[serde(tag = "type")]
+pub enum Node {
+    #[serde(rename = "ROOT")]
+    Root(NodeRoot),
+    #[serde(rename = "ARRAY")]
+    Array(NodeArray),
+    #[serde(rename = "STRING")]
+    String(NodeString),
+    #[serde(rename = "IMEMO")]
+    Imemo(NodeImemo),
+    #[serde(rename = "OBJECT")]
+    Object(NodeObject),
+    #[serde(rename = "REGEXP")]
+    Regexp(NodeRegexp),
+    #[serde(rename = "CLASS")]
+    Class(NodeClass),
+    #[serde(rename = "MODULE")]
+    Module(NodeModule),
+    #[serde(rename = "HASH")]
+    Hash(NodeHash),
+    #[serde(rename = "DATA")]
+    Data(NodeData),
+    #[serde(rename = "ICLASS")]
+    Iclass(NodeIclass),
+    #[serde(rename = "SYMBOL")]
+    Symbol(NodeSymbol),
+    #[serde(rename = "COMPLEX")]
+    Complex(NodeComplex),
+    #[serde(rename = "BIGNUM")]
+    Bignum(NodeBignum),
+    #[serde(rename = "FILE")]
+    File(NodeFile),
+    #[serde(rename = "FLOAT")]
+    Float(NodeFloat),
+    #[serde(rename = "RATIONAL")]
+    Rational(NodeRational),
+    #[serde(rename = "STRUCT")]
+    Struct(NodeStruct),
+    #[serde(rename = "MATCH")]
+    Match(NodeMatch),
+}
+
+// Expands $body for every heap object variant, binding the inner struct to $node.
+// Roots are not heap objects and evaluate $root instead.
+macro_rules! each_node {
+    ($self:expr, $node:ident => $body:expr, $root:ident => $root_body:expr) => {
+        match $self {
+            Node::Root($root) => $root_body,
+            Node::Array($node) => $body,
+            Node::String($node) => $body,
+            Node::Imemo($node) => $body,
+            Node::Object($node) => $body,
+            Node::Regexp($node) => $body,
+            Node::Class($node) => $body,
+            Node::Module($node) => $body,
+            Node::Hash($node) => $body,
+            Node::Data($node) => $body,
+            Node::Iclass($node) => $body,
+            Node::Symbol($node) => $body,
+            Node::Complex($node) => $body,
+            Node::Bignum($node) => $body,
+            Node::File($node) => $body,
+            Node::Float($node) => $body,
+            Node::Rational($node) => $body,
+            Node::Struct($node) => $body,
+            Node::Match($node) => $body,
+        }
+    };
+}
+
+// Layout of a dump line: a heap object's address, then the type tag, then the
+// fields of its struct. Roots have no address and start with the tag.
+#[derive(Serialize)]
+struct Record<'a, T> {
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "SerializeUtils::to_hex_opt")]
+    address: Option<HeapAddress>,
+    #[serde(rename = "type")]
+    type_name: &'static str,
+    #[serde(flatten)]
+    node: &'a T,
+}
+
+impl Serialize for Node {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let type_name = self.type_name();
+        each_node!(self,
+                   node => Record { address: Some(node.address), type_name, node }.serialize(serializer),
+                   root => Node::serialize_root(root, serializer))
+    }
+}
+
+impl Node {
+    // Fields the struct doesn't declare are collected into its flattened extra map
+    pub fn from_str(json_form: &str) -> Result<Self, Error> {
+        serde_json::from_str(json_form)
+    }
+
+    // Whether the typed struct for a dump type declares a field, None for unknown
+    // types. The "type" tag itself is not part of any struct.
+    pub fn declares_field(type_name: &str, field: &str) -> Option<bool> {
+        let declared = match type_name {
+            "ROOT" => DeserializeUtils::declares_field::<NodeRoot>(field),
+            "ARRAY" => DeserializeUtils::declares_field::<NodeArray>(field),
+            "STRING" => DeserializeUtils::declares_field::<NodeString>(field),
+            "IMEMO" => DeserializeUtils::declares_field::<NodeImemo>(field),
+            "OBJECT" => DeserializeUtils::declares_field::<NodeObject>(field),
+            "REGEXP" => DeserializeUtils::declares_field::<NodeRegexp>(field),
+            "CLASS" => DeserializeUtils::declares_field::<NodeClass>(field),
+            "MODULE" => DeserializeUtils::declares_field::<NodeModule>(field),
+            "HASH" => DeserializeUtils::declares_field::<NodeHash>(field),
+            "DATA" => DeserializeUtils::declares_field::<NodeData>(field),
+            "ICLASS" => DeserializeUtils::declares_field::<NodeIclass>(field),
+            "SYMBOL" => DeserializeUtils::declares_field::<NodeSymbol>(field),
+            "COMPLEX" => DeserializeUtils::declares_field::<NodeComplex>(field),
+            "BIGNUM" => DeserializeUtils::declares_field::<NodeBignum>(field),
+            "FILE" => DeserializeUtils::declares_field::<NodeFile>(field),
+            "FLOAT" => DeserializeUtils::declares_field::<NodeFloat>(field),
+            "RATIONAL" => DeserializeUtils::declares_field::<NodeRational>(field),
+            "STRUCT" => DeserializeUtils::declares_field::<NodeStruct>(field),
+            "MATCH" => DeserializeUtils::declares_field::<NodeMatch>(field),
+            _ => return None
+        };
+        Some(declared)
+    }
+
+    // HeapDump keeps roots apart from the nodes, merged by category
+    pub fn serialize_root<S>(root: &NodeRoot, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        Record { address: None, type_name: "ROOT", node: root }.serialize(serializer)
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Node::Root(_) => "ROOT",
+            Node::Array(_) => "ARRAY",
+            Node::String(_) => "STRING",
+            Node::Imemo(_) => "IMEMO",
+            Node::Object(_) => "OBJECT",
+            Node::Regexp(_) => "REGEXP",
+            Node::Class(_) => "CLASS",
+            Node::Module(_) => "MODULE",
+            Node::Hash(_) => "HASH",
+            Node::Data(_) => "DATA",
+            Node::Iclass(_) => "ICLASS",
+            Node::Symbol(_) => "SYMBOL",
+            Node::Complex(_) => "COMPLEX",
+            Node::Bignum(_) => "BIGNUM",
+            Node::File(_) => "FILE",
+            Node::Float(_) => "FLOAT",
+            Node::Rational(_) => "RATIONAL",
+            Node::Struct(_) => "STRUCT",
+            Node::Match(_) => "MATCH",
+        }
+    }
+
+    // Roots have no address of their own
+    pub fn address(&self) -> HeapAddress {
+        each_node!(self, node => node.address, _root => 0)
+    }
+
+    pub fn class(&self) -> Option<HeapAddress> {
+        match self {
+            Node::Root(_) => None,
+            Node::Array(node) => node.class,
+            Node::String(node) => Some(node.class),
+            Node::Imemo(node) => node.class,
+            Node::Object(node) => Some(node.class),
+            Node::Regexp(node) => Some(node.class),
+            Node::Class(node) => node.class,
+            Node::Module(node) => node.class,
+            Node::Hash(node) => node.class,
+            Node::Data(node) => node.class,
+            Node::Iclass(node) => Some(node.class),
+            Node::Symbol(node) => Some(node.class),
+            Node::Complex(node) => Some(node.class),
+            Node::Bignum(node) => Some(node.class),
+            Node::File(node) => Some(node.class),
+            Node::Float(node) => Some(node.class),
+            Node::Rational(node) => Some(node.class),
+            Node::Struct(node) => Some(node.class),
+            Node::Match(node) => Some(node.class),
+        }
+    }
+
+    pub fn references(&self) -> &[HeapAddress] {
+        match self {
+            Node::Root(node) => &node.references,
+            Node::Array(node) => &node.references,
+            Node::String(node) => &node.references,
+            Node::Imemo(node) => &node.references,
+            Node::Object(node) => &node.references,
+            Node::Regexp(node) => &node.references,
+            Node::Class(node) => &node.references,
+            Node::Module(node) => &node.references,
+            Node::Hash(node) => &node.references,
+            Node::Data(node) => &node.references,
+            Node::Iclass(node) => &node.references,
+            Node::File(node) => &node.references,
+            Node::Struct(node) => &node.references,
+            Node::Match(node) => &node.references,
+            Node::Symbol(_) | Node::Complex(_) | Node::Bignum(_) | Node::Float(_) | Node::Rational(_) => &[],
+        }
+    }
+
+    // None for the types that never reference other objects
+    pub fn references_mut(&mut self) -> Option<&mut Vec<HeapAddress>> {
+        match self {
+            Node::Root(node) => Some(&mut node.references),
+            Node::Array(node) => Some(&mut node.references),
+            Node::String(node) => Some(&mut node.references),
+            Node::Imemo(node) => Some(&mut node.references),
+            Node::Object(node) => Some(&mut node.references),
+            Node::Regexp(node) => Some(&mut node.references),
+            Node::Class(node) => Some(&mut node.references),
+            Node::Module(node) => Some(&mut node.references),
+            Node::Hash(node) => Some(&mut node.references),
+            Node::Data(node) => Some(&mut node.references),
+            Node::Iclass(node) => Some(&mut node.references),
+            Node::File(node) => Some(&mut node.references),
+            Node::Struct(node) => Some(&mut node.references),
+            Node::Match(node) => Some(&mut node.references),
+            Node::Symbol(_) | Node::Complex(_) | Node::Bignum(_) | Node::Float(_) | Node::Rational(_) => None,
+        }
+    }
+
+    // Fields the typed struct doesn't know, such as shape_id or slot_size from newer Rubies
+    pub fn extra(&self) -> &BTreeMap<String, Value> {
+        each_node!(self, node => &node.extra, root => &root.extra)
+    }
+
+    pub fn memsize(&self) -> usize {
+        each_node!(self, node => node.memsize, _root => 0)
+    }
+
+    pub fn flags(&self) -> Option<&Flags> {
+        match self {
+            Node::Root(_) => None,
+            Node::Array(node) => node.flags.as_ref(),
+            Node::Iclass(node) => node.flags.as_ref(),
+            Node::File(node) => node.flags.as_ref(),
+            Node::String(node) => Some(&node.flags),
+            Node::Imemo(node) => Some(&node.flags),
+            Node::Object(node) => Some(&node.flags),
+            Node::Regexp(node) => Some(&node.flags),
+            Node::Class(node) => Some(&node.flags),
+            Node::Module(node) => Some(&node.flags),
+            Node::Hash(node) => Some(&node.flags),
+            Node::Data(node) => Some(&node.flags),
+            Node::Symbol(node) => Some(&node.flags),
+            Node::Complex(node) => Some(&node.flags),
+            Node::Bignum(node) => Some(&node.flags),
+            Node::Float(node) => Some(&node.flags),
+            Node::Rational(node) => Some(&node.flags),
+            Node::Struct(node) => Some(&node.flags),
+            Node::Match(node) => Some(&node.flags),
+        }
+    }
+
+    pub fn is_old(&self) -> bool {
+        self.flags().and_then(|flags| flags.old).unwrap_or(false)
+    }
+
+    pub fn is_uncollectible(&self) -> bool {
+        self.flags().and_then(|flags| flags.uncollectible).unwrap_or(false)
+    }
+
+    pub fn file(&self) -> Option<&str> {
+        each_node!(self, node => node.file.as_deref(), _root => None)
+    }
+
+    pub fn line(&self) -> Option<usize> {
+        each_node!(self, node => node.line, _root => None)
+    }
+
+    pub fn method(&self) -> Option<&str> {
+        each_node!(self, node => node.method.as_deref(), _root => None)
+    }
+
+    pub fn generation(&self) -> Option<usize> {
+        each_node!(self, node => node.generation, _root => None)
+    }
+
+    // "file:line" of the allocation, present only when allocation tracing was enabled
+    pub fn allocation_site(&self) -> Option<String> {
+        let file = self.file()?;
+        match self.line() {
+            Some(line) => Some(format!("{}:{}", file, line)),
+            None => Some(String::from(file))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_deserializes_by_type() {
+    let node_res = Node::from_str(r#"{"address":"0x7fc9748b34f0", "type":"STRING", "class":"0x7fc9690cfb90", "file":"app.rb", "line":3, "memsize":40, "flags":{"wb_protected":true}}"#);
+    assert!(node_res.is_ok());
+
+    let node = node_res.unwrap();
+    assert_eq!(node.type_name(), "STRING");
+    assert_eq!(node.address(), 140503220434160 as HeapAddress);
+    assert_eq!(node.class(), Some(140503027612560 as HeapAddress));
+    assert_eq!(node.memsize(), 40);
+    assert_eq!(node.file(), Some("app.rb"));
+    assert_eq!(node.line(), Some(3));
+    assert_eq!(node.allocation_site(), Some(String::from("app.rb:3")));
+  }
+
+  #[test]
+  fn it_deserializes_roots() {
+    let node = Node::from_str(r#"{"type":"ROOT", "root":"vm", "references":["0x7fc969077fa8"]}"#).unwrap();
+    assert_eq!(node.type_name(), "ROOT");
+    assert_eq!(node.references(), &[140503027253160 as HeapAddress]);
+    assert_eq!(node.memsize(), 0);
+  }
+
+  #[test]
+  fn it_fails_to_deserialize_unknown_types() {
+    let node_res = Node::from_str(r#"{"address":"0x7fc9748b34f0", "type":"ZOMBIE", "memsize":40}"#);
+    assert!(node_res.is_err());
+  }
+
+  #[test]
+  fn it_keeps_unknown_fields() {
+    let node = Node::from_str(r#"{"address":"0x7fc9748b34f0", "type":"ARRAY", "length":0, "memsize":40, "shape_id":7, "slot_size":40}"#).unwrap();
+    assert_eq!(node.extra().get("shape_id"), Some(&Value::from(7)));
+    assert_eq!(node.extra().keys().collect::<Vec<_>>(), vec!["shape_id", "slot_size"]);
+  }
+
+  #[test]
+  fn it_knows_declared_fields() {
+    assert_eq!(Node::declares_field("ROOT", "references"), Some(true));
+    assert_eq!(Node::declares_field("STRING", "bytesize"), Some(true));
+    assert_eq!(Node::declares_field("STRING", "flags"), Some(true));
+    assert_eq!(Node::declares_field("FLOAT", "value"), Some(true));
+    assert_eq!(Node::declares_field("IMEMO", "imemo_type"), Some(true));
+    assert_eq!(Node::declares_field("ARRAY", "shape_id"), Some(false));
+    assert_eq!(Node::declares_field("ARRAY", "extra"), Some(false));
+    assert_eq!(Node::declares_field("ZOMBIE", "address"), None);
+  }
+}
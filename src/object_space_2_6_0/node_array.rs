@@ -1,26 +1,40 @@
-use serde_json::Error;
+use std::collections::BTreeMap;
+use serde_json::{Error, Value};
 use crate::heap_address::HeapAddress;
 use crate::deserialize_utils::DeserializeUtils;
+use crate::serialize_utils::SerializeUtils;
 use super::flags::Flags;
 
-#[derive(Hash, Eq, PartialEq, Debug, Deserialize)]
-struct NodeArray {
-    #[serde(deserialize_with = "DeserializeUtils::from_hex")]
-    address: HeapAddress,
+#[derive(Hash, Eq, PartialEq, Debug, Deserialize, Serialize)]
+pub struct NodeArray {
+    #[serde(skip_serializing, deserialize_with = "DeserializeUtils::from_hex")]
+    pub address: HeapAddress,
     #[serde(default, deserialize_with = "DeserializeUtils::from_hex_opt")]
-    class: Option<HeapAddress>,
-    frozen: Option<bool>,
-    length: usize,
-    embedded: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "SerializeUtils::to_hex_opt")]
+    pub class: Option<HeapAddress>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frozen: Option<bool>,
+    pub length: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shared: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub embedded: Option<bool>,
     #[serde(default, deserialize_with = "DeserializeUtils::from_hex_array")]
-    references: Vec<HeapAddress>,
-    memsize: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty", serialize_with = "SerializeUtils::to_hex_array")]
+    pub references: Vec<HeapAddress>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub generation: Option<usize>,
+    pub memsize: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub flags: Option<Flags>,
-    file: Option<String>,
-    line: Option<usize>,
-    method: Option<String>,
-    generation: Option<usize>,
-    shared: Option<bool>,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
 }
 
 impl NodeArray {
@@ -1,21 +1,30 @@
-use serde_json::Error;
+use std::collections::BTreeMap;
+use serde_json::{Error, Value};
 use crate::heap_address::HeapAddress;
 use crate::deserialize_utils::DeserializeUtils;
+use crate::serialize_utils::SerializeUtils;
 use super::flags::Flags;
 
-#[derive(Hash, Eq, PartialEq, Debug, Deserialize)]
+#[derive(Hash, Eq, PartialEq, Debug, Deserialize, Serialize)]
 pub struct NodeBignum {
+    #[serde(skip_serializing, deserialize_with = "DeserializeUtils::from_hex")]
+    pub address: HeapAddress,
     #[serde(deserialize_with = "DeserializeUtils::from_hex")]
-    address: HeapAddress,
-    #[serde(deserialize_with = "DeserializeUtils::from_hex")]
-    class: HeapAddress,
-    frozen: bool,
-    memsize: usize,
+    #[serde(serialize_with = "SerializeUtils::to_hex")]
+    pub class: HeapAddress,
+    pub frozen: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub generation: Option<usize>,
+    pub memsize: usize,
     pub flags: Flags,
-    file: Option<String>,
-    line: Option<usize>,
-    method: Option<String>,
-    generation: Option<usize>,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
 }
 
 impl NodeBignum {
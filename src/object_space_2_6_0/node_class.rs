@@ -1,24 +1,39 @@
-use serde_json::Error;
+use std::collections::BTreeMap;
+use serde_json::{Error, Value};
 use crate::heap_address::HeapAddress;
 use crate::deserialize_utils::DeserializeUtils;
+use crate::serialize_utils::SerializeUtils;
 use super::flags::Flags;
 
-#[derive(Hash, Eq, PartialEq, Debug, Deserialize)]
-struct NodeClass {
-    #[serde(deserialize_with = "DeserializeUtils::from_hex")]
-    address: HeapAddress,
+#[derive(Hash, Eq, PartialEq, Debug, Deserialize, Serialize)]
+pub struct NodeClass {
+    #[serde(skip_serializing, deserialize_with = "DeserializeUtils::from_hex")]
+    pub address: HeapAddress,
     #[serde(default, deserialize_with = "DeserializeUtils::from_hex_opt")]
-    class: Option<HeapAddress>,
-    name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "SerializeUtils::to_hex_opt")]
+    pub class: Option<HeapAddress>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frozen: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    // Only emitted by newer Rubies
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub singleton: Option<bool>,
     #[serde(default, deserialize_with = "DeserializeUtils::from_hex_array")]
-    references: Vec<HeapAddress>,
-    memsize: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty", serialize_with = "SerializeUtils::to_hex_array")]
+    pub references: Vec<HeapAddress>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub generation: Option<usize>,
+    pub memsize: usize,
     pub flags: Flags,
-    frozen: Option<bool>,
-    file: Option<String>,
-    line: Option<usize>,
-    method: Option<String>,
-    generation: Option<usize>,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
 }
 
 impl NodeClass {
@@ -86,6 +101,14 @@ mod tests {
     assert_eq!(flags.marking, None);
   }
 
+  #[test]
+  fn it_deserializes_singleton() {
+    let json_str = r#"{"address":"0x7f809035e478", "memsize":1408, "flags":{}, "singleton":true}"#;
+    let node_class = NodeClass::from_str(json_str).unwrap();
+    assert_eq!(node_class.singleton, Some(true));
+    assert_eq!(NodeClass::from_str(r#"{"address":"0x7f809035e478", "memsize":1408, "flags":{}}"#).unwrap().singleton, None);
+  }
+
   #[test]
   fn it_fails_to_deserialize() {
     let node_class_res = NodeClass::from_str(r#""type":"ROOT", "root":"vm", "references":["0x7fc969077fa8"]}"#);
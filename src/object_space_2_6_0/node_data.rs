@@ -1,26 +1,38 @@
-use serde_json::Error;
+use std::collections::BTreeMap;
+use serde_json::{Error, Value};
 use crate::heap_address::HeapAddress;
 use crate::deserialize_utils::DeserializeUtils;
+use crate::serialize_utils::SerializeUtils;
 use super::flags::Flags;
 
 // Wrapped C pointers
-#[derive(Hash, Eq, PartialEq, Debug, Deserialize)]
-struct NodeData {
-    #[serde(deserialize_with = "DeserializeUtils::from_hex")]
-    address: HeapAddress,
-    #[serde(default, deserialize_with = "DeserializeUtils::from_hex_array")]
-    references: Vec<HeapAddress>,
+#[derive(Hash, Eq, PartialEq, Debug, Deserialize, Serialize)]
+pub struct NodeData {
+    #[serde(skip_serializing, deserialize_with = "DeserializeUtils::from_hex")]
+    pub address: HeapAddress,
     #[serde(default, deserialize_with = "DeserializeUtils::from_hex_opt")]
-    class: Option<HeapAddress>,
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "SerializeUtils::to_hex_opt")]
+    pub class: Option<HeapAddress>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frozen: Option<bool>,
     #[serde(rename="struct")]
-    struct_type: Option<String>,
-    memsize: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub struct_type: Option<String>,
+    #[serde(default, deserialize_with = "DeserializeUtils::from_hex_array")]
+    #[serde(skip_serializing_if = "Vec::is_empty", serialize_with = "SerializeUtils::to_hex_array")]
+    pub references: Vec<HeapAddress>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub generation: Option<usize>,
+    pub memsize: usize,
     pub flags: Flags,
-    file: Option<String>,
-    line: Option<usize>,
-    method: Option<String>,
-    generation: Option<usize>,
-    frozen: Option<bool>,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
 }
 
 impl NodeData {
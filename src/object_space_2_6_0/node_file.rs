@@ -1,24 +1,37 @@
-use serde_json::Error;
+use std::collections::BTreeMap;
+use serde_json::{Error, Value};
 use crate::heap_address::HeapAddress;
 use crate::deserialize_utils::DeserializeUtils;
+use crate::serialize_utils::SerializeUtils;
 use super::flags::Flags;
 
-#[derive(Hash, Eq, PartialEq, Debug, Deserialize)]
-struct NodeFile {
+#[derive(Hash, Eq, PartialEq, Debug, Deserialize, Serialize)]
+pub struct NodeFile {
+    #[serde(skip_serializing, deserialize_with = "DeserializeUtils::from_hex")]
+    pub address: HeapAddress,
     #[serde(deserialize_with = "DeserializeUtils::from_hex")]
-    address: HeapAddress,
-    #[serde(deserialize_with = "DeserializeUtils::from_hex")]
-    class: HeapAddress,
+    #[serde(serialize_with = "SerializeUtils::to_hex")]
+    pub class: HeapAddress,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frozen: Option<bool>,
+    // -1 once the IO is closed
+    pub fd: i64,
     #[serde(default, deserialize_with = "DeserializeUtils::from_hex_array")]
-    references: Vec<HeapAddress>,
-    memsize: usize,
-    fd: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty", serialize_with = "SerializeUtils::to_hex_array")]
+    pub references: Vec<HeapAddress>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub generation: Option<usize>,
+    pub memsize: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub flags: Option<Flags>,
-    frozen: Option<bool>,
-    file: Option<String>,
-    line: Option<usize>,
-    method: Option<String>,
-    generation: Option<usize>,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
 }
 
 impl NodeFile {
@@ -47,7 +60,7 @@ mod tests {
     assert_eq!(node_file.line, Some(947 as usize));
     assert_eq!(node_file.method, Some(String::from("open")));
     assert_eq!(node_file.generation, Some(65 as usize));
-    assert_eq!(node_file.fd, 27 as usize);
+    assert_eq!(node_file.fd, 27);
     assert_eq!(node_file.frozen, None);
 
     let flags_opt = node_file.flags;
@@ -70,7 +83,7 @@ mod tests {
     assert_eq!(node_file.line, None);
     assert_eq!(node_file.method, None);
     assert_eq!(node_file.generation, None);
-    assert_eq!(node_file.fd, 27 as usize);
+    assert_eq!(node_file.fd, 27);
     assert_eq!(node_file.frozen, Some(true));
 
     let flags_opt = node_file.flags;
@@ -83,6 +96,12 @@ mod tests {
     assert_eq!(flags.marking, Some(true));
   }
 
+  #[test]
+  fn it_deserializes_closed_files() {
+    let node_file_res = NodeFile::from_str(r#"{"address":"0x7f809035e478", "class":"0x7f80912f5800", "fd":-1, "memsize":232}"#);
+    assert_eq!(node_file_res.unwrap().fd, -1);
+  }
+
   #[test]
   fn it_fails_to_deserialize() {
     let node_file_res = NodeFile::from_str(r#""type":"ROOT", "root":"vm", "references":["0x7fc969077fa8"]}"#);
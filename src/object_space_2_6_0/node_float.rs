@@ -1,22 +1,32 @@
-use serde_json::Error;
+use std::collections::BTreeMap;
+use serde_json::{Error, Value};
 use crate::heap_address::HeapAddress;
 use crate::deserialize_utils::DeserializeUtils;
+use crate::serialize_utils::SerializeUtils;
 use super::flags::Flags;
+use super::float_value::FloatValue;
 
-#[derive(Hash, Eq, PartialEq, Debug, Deserialize)]
+#[derive(Hash, Eq, PartialEq, Debug, Deserialize, Serialize)]
 pub struct NodeFloat {
+    #[serde(skip_serializing, deserialize_with = "DeserializeUtils::from_hex")]
+    pub address: HeapAddress,
     #[serde(deserialize_with = "DeserializeUtils::from_hex")]
-    address: HeapAddress,
-    #[serde(deserialize_with = "DeserializeUtils::from_hex")]
-    class: HeapAddress,
-    frozen: bool,
-    memsize: usize,
-    value: String, // "nan", "inf", "-inf" "1.79769e+308"
+    #[serde(serialize_with = "SerializeUtils::to_hex")]
+    pub class: HeapAddress,
+    pub frozen: bool,
+    pub value: FloatValue, // "nan", "inf", "-inf" "1.79769e+308"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub generation: Option<usize>,
+    pub memsize: usize,
     pub flags: Flags,
-    file: Option<String>,
-    line: Option<usize>,
-    method: Option<String>,
-    generation: Option<usize>,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
 }
 
 impl NodeFloat {
@@ -38,7 +48,7 @@ mod tests {
     let node_float = node_float_res.unwrap();
     assert_eq!(node_float.address, 140503184026880 as HeapAddress);
     assert_eq!(node_float.class, 140503027599240 as HeapAddress);
-    assert_eq!(node_float.value, String::from("1e+100"));
+    assert_eq!(node_float.value, FloatValue(1e100));
 
     assert_eq!(node_float.file, Some(String::from("(irb)")));
     assert_eq!(node_float.line, Some(11 as usize));
@@ -64,7 +74,7 @@ mod tests {
     let node_float = node_float_res.unwrap();
     assert_eq!(node_float.address, 140503184026880 as HeapAddress);
     assert_eq!(node_float.class, 140503027599240 as HeapAddress);
-    assert_eq!(node_float.value, String::from("1e+100"));
+    assert_eq!(node_float.value, FloatValue(1e100));
 
     assert_eq!(node_float.file, None);
     assert_eq!(node_float.line, None);
@@ -81,6 +91,12 @@ mod tests {
     assert_eq!(flags.marking, None);
   }
 
+  #[test]
+  fn it_deserializes_special_values() {
+    let node_float = NodeFloat::from_str(r#"{"address":"0x7fc9725fad00", "class":"0x7fc9690cc788", "frozen":true, "value":"-inf", "memsize":40, "flags":{}}"#).unwrap();
+    assert_eq!(node_float.value, FloatValue(f64::NEG_INFINITY));
+  }
+
   #[test]
   fn it_fails_to_deserialize() {
     let node_float_res = NodeFloat::from_str(r#""type":"ROOT", "root":"vm", "references":["0x7fc969077fa8"]}"#);
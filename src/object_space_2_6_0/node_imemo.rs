@@ -1,24 +1,36 @@
-use serde_json::Error;
+use std::collections::BTreeMap;
+use serde_json::{Error, Value};
 use crate::heap_address::HeapAddress;
 use crate::deserialize_utils::DeserializeUtils;
+use crate::serialize_utils::SerializeUtils;
 use super::flags::Flags;
+use super::imemo_type::ImemoType;
 
-#[derive(Hash, Eq, PartialEq, Debug, Deserialize)]
-struct NodeImemo {
-    #[serde(deserialize_with = "DeserializeUtils::from_hex")]
-    address: HeapAddress,
+#[derive(Hash, Eq, PartialEq, Debug, Deserialize, Serialize)]
+pub struct NodeImemo {
+    #[serde(skip_serializing, deserialize_with = "DeserializeUtils::from_hex")]
+    pub address: HeapAddress,
     #[serde(default, deserialize_with = "DeserializeUtils::from_hex_opt")]
-    class: Option<HeapAddress>,
-    imemo_type: String, // TODO: enum
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "SerializeUtils::to_hex_opt")]
+    pub class: Option<HeapAddress>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frozen: Option<bool>,
+    pub imemo_type: ImemoType,
     #[serde(default, deserialize_with = "DeserializeUtils::from_hex_array")]
-    references: Vec<HeapAddress>,
-    memsize: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty", serialize_with = "SerializeUtils::to_hex_array")]
+    pub references: Vec<HeapAddress>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub generation: Option<usize>,
+    pub memsize: usize,
     pub flags: Flags,
-    frozen: Option<bool>,
-    file: Option<String>,
-    line: Option<usize>,
-    method: Option<String>,
-    generation: Option<usize>,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
 }
 
 impl NodeImemo {
@@ -40,7 +52,7 @@ mod tests {
     let node_imemo = node_imemo_res.unwrap();
     assert_eq!(node_imemo.address, 140503220434160 as HeapAddress);
     assert_eq!(node_imemo.class, None);
-    assert_eq!(node_imemo.imemo_type, String::from("iseq"));
+    assert_eq!(node_imemo.imemo_type, ImemoType::Iseq);
     assert_eq!(node_imemo.references, &[140503027612560 as HeapAddress]);
     assert_eq!(node_imemo.memsize, 40);
 
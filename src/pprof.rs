@@ -0,0 +1,254 @@
+use std::collections::HashMap;
+use std::io;
+use std::io::Write;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use super::heap_dump::HeapDump;
+use super::object_space_2_6_0::node::Node;
+
+// Minimal protobuf wire format encoder, enough for profile.proto
+struct ProtobufWriter {
+    buffer: Vec<u8>,
+}
+
+impl ProtobufWriter {
+    fn new() -> Self {
+        ProtobufWriter { buffer: vec![] }
+    }
+
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.buffer.push((value as u8 & 0x7f) | 0x80);
+            value >>= 7;
+        }
+        self.buffer.push(value as u8);
+    }
+
+    // Zero values are the protobuf default and are left out
+    fn uint64(&mut self, field: u64, value: u64) {
+        if value == 0 { return; }
+        self.varint(field << 3);
+        self.varint(value);
+    }
+
+    fn int64(&mut self, field: u64, value: i64) {
+        self.uint64(field, value as u64);
+    }
+
+    fn bytes(&mut self, field: u64, bytes: &[u8]) {
+        self.varint(field << 3 | 2);
+        self.varint(bytes.len() as u64);
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    fn packed(&mut self, field: u64, values: &[u64]) {
+        if values.is_empty() { return; }
+        let mut packed = ProtobufWriter::new();
+        for value in values { packed.varint(*value); }
+        self.bytes(field, &packed.buffer);
+    }
+}
+
+struct Sample {
+    location_ids: Vec<u64>,
+    objects: i64,
+    space: i64,
+    labels: Vec<(i64, i64)>,
+}
+
+// pprof Profile with "objects" (count) and "space" (bytes) sample values.
+// Each sample's stack is the allocation file/line/method, optionally topped with a class frame.
+pub struct PprofProfile {
+    strings: Vec<String>,
+    string_ids: HashMap<String, i64>,
+    // (name, filename) -> function id
+    functions: HashMap<(i64, i64), u64>,
+    // (function id, line) -> location id
+    locations: HashMap<(u64, i64), u64>,
+    samples: Vec<Sample>,
+    sample_ids: HashMap<Vec<u64>, usize>,
+}
+
+impl PprofProfile {
+    pub fn new(heap_dump: &HeapDump, per_object: bool, class_frame: bool) -> Self {
+        let mut profile = PprofProfile {
+            strings: vec![String::new()],
+            string_ids: HashMap::new(),
+            functions: HashMap::new(),
+            locations: HashMap::new(),
+            samples: vec![],
+            sample_ids: HashMap::new(),
+        };
+        profile.string_ids.insert(String::new(), 0);
+
+        let mut nodes: Vec<&Node> = heap_dump.objects().collect();
+        nodes.sort_by_key(|node| node.address());
+        for node in nodes {
+            let mut location_ids = vec![];
+            if class_frame {
+                let class = heap_dump.label(node);
+                location_ids.push(profile.location(&class, "", 0));
+            }
+            let method = node.method().unwrap_or("(unknown)");
+            let file = node.file().unwrap_or("(unknown)");
+            location_ids.push(profile.location(method, file, node.line().unwrap_or(0) as i64));
+
+            if per_object {
+                let key = profile.string("address");
+                let address = profile.string(&format!("0x{:x}", node.address()));
+                profile.add_sample(location_ids, node.memsize(), vec![(key, address)]);
+            } else {
+                profile.add_sample(location_ids, node.memsize(), vec![]);
+            }
+        }
+        profile
+    }
+
+    fn string(&mut self, value: &str) -> i64 {
+        if let Some(id) = self.string_ids.get(value) { return *id; }
+        let id = self.strings.len() as i64;
+        self.strings.push(String::from(value));
+        self.string_ids.insert(String::from(value), id);
+        id
+    }
+
+    fn location(&mut self, name: &str, filename: &str, line: i64) -> u64 {
+        let function_key = (self.string(name), self.string(filename));
+        let next_function_id = self.functions.len() as u64 + 1;
+        let function_id = *self.functions.entry(function_key).or_insert(next_function_id);
+
+        let next_location_id = self.locations.len() as u64 + 1;
+        *self.locations.entry((function_id, line)).or_insert(next_location_id)
+    }
+
+    // Samples without labels sharing a stack are merged
+    fn add_sample(&mut self, location_ids: Vec<u64>, memsize: usize, labels: Vec<(i64, i64)>) {
+        if labels.is_empty() {
+            if let Some(index) = self.sample_ids.get(&location_ids) {
+                let sample = &mut self.samples[*index];
+                sample.objects += 1;
+                sample.space += memsize as i64;
+                return;
+            }
+            self.sample_ids.insert(location_ids.clone(), self.samples.len());
+        }
+        self.samples.push(Sample { location_ids, objects: 1, space: memsize as i64, labels });
+    }
+
+    pub fn encode(&mut self) -> Vec<u8> {
+        let value_types = [
+            (self.string("objects"), self.string("count")),
+            (self.string("space"), self.string("bytes")),
+        ];
+        let mut profile = ProtobufWriter::new();
+
+        for (value_type, unit) in &value_types {
+            let mut message = ProtobufWriter::new();
+            message.int64(1, *value_type);
+            message.int64(2, *unit);
+            profile.bytes(1, &message.buffer);
+        }
+
+        for sample in &self.samples {
+            let mut message = ProtobufWriter::new();
+            message.packed(1, &sample.location_ids);
+            message.packed(2, &[sample.objects as u64, sample.space as u64]);
+            for (key, value) in &sample.labels {
+                let mut label = ProtobufWriter::new();
+                label.int64(1, *key);
+                label.int64(2, *value);
+                message.bytes(3, &label.buffer);
+            }
+            profile.bytes(2, &message.buffer);
+        }
+
+        let mut locations: Vec<(&(u64, i64), &u64)> = self.locations.iter().collect();
+        locations.sort_by_key(|(_, id)| **id);
+        for ((function_id, line_number), id) in locations {
+            let mut line = ProtobufWriter::new();
+            line.uint64(1, *function_id);
+            line.int64(2, *line_number);
+            let mut message = ProtobufWriter::new();
+            message.uint64(1, *id);
+            message.bytes(4, &line.buffer);
+            profile.bytes(4, &message.buffer);
+        }
+
+        let mut functions: Vec<(&(i64, i64), &u64)> = self.functions.iter().collect();
+        functions.sort_by_key(|(_, id)| **id);
+        for ((name, filename), id) in functions {
+            let mut message = ProtobufWriter::new();
+            message.uint64(1, *id);
+            message.int64(2, *name);
+            message.int64(3, *name);
+            message.int64(4, *filename);
+            profile.bytes(5, &message.buffer);
+        }
+
+        for string in &self.strings {
+            profile.bytes(6, string.as_bytes());
+        }
+
+        // default_sample_type, "space"
+        profile.int64(14, value_types[1].0);
+        profile.buffer
+    }
+
+    pub fn write(&mut self, out: &mut dyn Write, gzip: bool) -> io::Result<()> {
+        let encoded = self.encode();
+        if gzip {
+            let mut encoder = GzEncoder::new(out, Compression::default());
+            encoder.write_all(&encoded)?;
+            encoder.finish()?;
+            Ok(())
+        } else {
+            out.write_all(&encoded)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn heap_dump() -> HeapDump {
+    let mut heap_dump = HeapDump::default();
+    heap_dump.add_line(String::from(r#"{"address":"0xa", "type":"ARRAY", "length":0, "memsize":10, "file":"app.rb", "line":3, "method":"call"}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xb", "type":"ARRAY", "length":0, "memsize":30, "file":"app.rb", "line":3, "method":"call"}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xc", "type":"ARRAY", "length":0, "memsize":5}"#));
+    heap_dump
+  }
+
+  #[test]
+  fn it_encodes_varints() {
+    let mut writer = ProtobufWriter::new();
+    writer.varint(1);
+    writer.varint(300);
+    assert_eq!(writer.buffer, vec![0x01, 0xac, 0x02]);
+  }
+
+  #[test]
+  fn it_aggregates_samples_per_site() {
+    let profile = PprofProfile::new(&heap_dump(), false, false);
+    assert_eq!(profile.samples.len(), 2);
+    assert_eq!(profile.samples[0].objects, 2);
+    assert_eq!(profile.samples[0].space, 40);
+    assert_eq!(profile.strings[0], "");
+  }
+
+  #[test]
+  fn it_keeps_samples_per_object() {
+    let profile = PprofProfile::new(&heap_dump(), true, true);
+    assert_eq!(profile.samples.len(), 3);
+    assert_eq!(profile.samples[0].location_ids.len(), 2);
+    assert_eq!(profile.samples[0].labels.len(), 1);
+  }
+
+  #[test]
+  fn it_encodes_the_string_table() {
+    let encoded = PprofProfile::new(&heap_dump(), false, false).encode();
+    // string_table entry "app.rb", field 6, length delimited
+    let needle = [0x32, 6, b'a', b'p', b'p', b'.', b'r', b'b'];
+    assert!(encoded.windows(needle.len()).any(|window| window == needle));
+  }
+}
@@ -0,0 +1,146 @@
+use std::collections::HashSet;
+use std::io;
+use std::io::Write;
+use super::dominator_tree::DominatorTree;
+use super::heap_address::HeapAddress;
+use super::heap_dump::HeapDump;
+use super::heap_graph::HeapGraph;
+use super::object_space_2_6_0::imemo_type::ImemoType;
+use super::object_space_2_6_0::node::Node;
+use super::totals::Totals;
+
+// Captured values listed per proc
+const CAPTURED_LIMIT: usize = 5;
+
+pub struct Captured {
+    pub address: HeapAddress,
+    pub label: String,
+    pub retained: usize,
+}
+
+pub struct Closure {
+    pub address: HeapAddress,
+    pub kind: String,
+    pub created_at: String,
+    pub retained: usize,
+    pub captured: Vec<Captured>,
+}
+
+// Procs and bindings with what their captured environment keeps alive.
+// A proc references its self, its iseq and its env imemo; the env holds the
+// local variables of the block and every enclosing scope through its parent envs.
+pub struct ProcsReport {
+    closures: Vec<Closure>,
+    by_site: Totals,
+}
+
+impl ProcsReport {
+    pub fn new(heap_dump: &HeapDump) -> Self {
+        let graph = HeapGraph::new(heap_dump);
+        let dominator_tree = DominatorTree::new(heap_dump, &graph);
+        let retained = |address| graph.index_of(address).map(|index| dominator_tree.retained_size(index)).unwrap_or(0);
+
+        let mut closures = vec![];
+        let mut by_site = Totals::default();
+        for node in heap_dump.objects() {
+            let kind = match node {
+                Node::Data(node_data) => match node_data.struct_type.as_deref() {
+                    Some(kind @ "proc") | Some(kind @ "binding") => kind,
+                    _ => continue
+                },
+                _ => continue
+            };
+            // The iseq's site is where its file was compiled, not where the block is
+            let created_at = node.allocation_site().unwrap_or_else(|| String::from("(unknown)"));
+
+            let mut captured: Vec<Captured> = Self::captured(heap_dump, node).into_iter()
+                .map(|address| Captured {
+                    address,
+                    label: heap_dump.get(address).map(|captured| heap_dump.label(captured)).unwrap_or_default(),
+                    retained: retained(address),
+                })
+                .collect();
+            captured.sort_by(|a, b| b.retained.cmp(&a.retained).then(a.address.cmp(&b.address)));
+
+            let closure = Closure { address: node.address(), kind: String::from(kind), created_at, retained: retained(node.address()), captured };
+            by_site.add(closure.created_at.clone(), closure.retained);
+            closures.push(closure);
+        }
+        closures.sort_by(|a, b| b.retained.cmp(&a.retained).then(a.address.cmp(&b.address)));
+        ProcsReport { closures, by_site }
+    }
+
+    fn imemo_type(node: &Node) -> Option<&ImemoType> {
+        match node {
+            Node::Imemo(node_imemo) => Some(&node_imemo.imemo_type),
+            _ => None
+        }
+    }
+
+    // Objects referenced from the proc's env and its parent envs, skipping code
+    fn captured(heap_dump: &HeapDump, node: &Node) -> Vec<HeapAddress> {
+        let mut visited = HashSet::new();
+        let mut captured = vec![];
+        let mut envs: Vec<&Node> = node.references().iter()
+            .filter_map(|reference| heap_dump.get(*reference))
+            .filter(|referenced| Self::imemo_type(referenced) == Some(&ImemoType::Env))
+            .collect();
+
+        while let Some(env) = envs.pop() {
+            if !visited.insert(env.address()) { continue; }
+            for reference in env.references() {
+                match heap_dump.get(*reference) {
+                    Some(referenced) if Self::imemo_type(referenced) == Some(&ImemoType::Env) => envs.push(referenced),
+                    Some(referenced) if Self::imemo_type(referenced).map(ImemoType::is_code).unwrap_or(false) => {}
+                    Some(_) if *reference != node.address() && visited.insert(*reference) => captured.push(*reference),
+                    _ => {}
+                }
+            }
+        }
+        captured
+    }
+
+    pub fn write(&self, out: &mut dyn Write, limit: usize) -> io::Result<()> {
+        Totals::write(out, "Procs and bindings by creation site (retained bytes)", &self.by_site.by_memsize(limit))?;
+
+        writeln!(out, "Largest procs and bindings by retained size:")?;
+        for closure in self.closures.iter().take(limit) {
+            writeln!(out, "  {:>10} bytes  0x{:x} {} {}", closure.retained, closure.address, closure.kind, closure.created_at)?;
+            for captured in closure.captured.iter().take(CAPTURED_LIMIT) {
+                writeln!(out, "    {:>10} bytes  captures 0x{:x} {}", captured.retained, captured.address, captured.label)?;
+            }
+            if closure.captured.len() > CAPTURED_LIMIT {
+                writeln!(out, "    ... {} more captured", closure.captured.len() - CAPTURED_LIMIT)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_finds_captured_objects() {
+    let mut heap_dump = HeapDump::default();
+    heap_dump.add_line(String::from(r#"{"type":"ROOT", "root":"vm", "references":["0xa"]}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xa", "type":"DATA", "struct":"proc", "references":["0xb", "0xc"], "memsize":80, "file":"app.rb", "line":4, "flags":{}}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xb", "type":"IMEMO", "imemo_type":"iseq", "memsize":500, "flags":{}}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xc", "type":"IMEMO", "imemo_type":"env", "references":["0xd", "0xe", "0xb"], "memsize":64, "flags":{}}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xd", "type":"IMEMO", "imemo_type":"env", "references":["0xf"], "memsize":64, "flags":{}}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xe", "type":"STRING", "class":"0x1", "memsize":40, "flags":{}}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xf", "type":"ARRAY", "length":0, "memsize":1000}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0x10", "type":"DATA", "struct":"proc", "references":["0x11"], "memsize":80, "flags":{}}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0x11", "type":"IMEMO", "imemo_type":"iseq", "memsize":500, "file":"app.rb", "line":1, "flags":{}}"#));
+
+    let report = ProcsReport::new(&heap_dump);
+    let closure = report.closures.iter().find(|closure| closure.address == 0x10).unwrap();
+    assert_eq!(closure.created_at, "(unknown)");
+    let closure = &report.closures[0];
+    assert_eq!(closure.created_at, "app.rb:4");
+    assert_eq!(closure.retained, 80 + 500 + 64 + 64 + 40 + 1000);
+    let captured: Vec<(HeapAddress, usize)> = closure.captured.iter().map(|captured| (captured.address, captured.retained)).collect();
+    assert_eq!(captured, vec![(0xf, 1000), (0xe, 40)]);
+  }
+}
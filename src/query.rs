@@ -0,0 +1,84 @@
+use super::heap_dump::HeapDump;
+use super::object_space_2_6_0::node::Node;
+
+#[derive(PartialEq, Debug)]
+enum Term {
+    Type(String),
+    Class(String),
+    Site(String),
+    MinMemsize(usize),
+    Generation(usize),
+}
+
+// Space separated "key:value" filters which all have to match, e.g.
+// "type:STRING site:app/models min_memsize:100".
+// Supported keys: type, class, site (substring of "file:line"), min_memsize, generation.
+#[derive(PartialEq, Debug)]
+pub struct Query {
+    terms: Vec<Term>,
+}
+
+impl Query {
+    pub fn parse(query: &str) -> Result<Self, String> {
+        let mut terms = vec![];
+        for term in query.split_whitespace() {
+            let (key, value) = match term.find(':') {
+                Some(split_at) => (&term[..split_at], &term[split_at + 1..]),
+                None => return Err(format!("Expected key:value, got '{}'", term))
+            };
+            terms.push(match key {
+                "type" => Term::Type(value.to_uppercase()),
+                "class" => Term::Class(String::from(value)),
+                "site" => Term::Site(String::from(value)),
+                "min_memsize" => Term::MinMemsize(Self::number(key, value)?),
+                "generation" => Term::Generation(Self::number(key, value)?),
+                _ => return Err(format!("Unknown query key '{}'", key))
+            });
+        }
+        Ok(Query { terms })
+    }
+
+    fn number(key: &str, value: &str) -> Result<usize, String> {
+        value.parse().map_err(|_| format!("{} expects a number, got '{}'", key, value))
+    }
+
+    pub fn matches(&self, heap_dump: &HeapDump, node: &Node) -> bool {
+        self.terms.iter().all(|term| match term {
+            Term::Type(type_name) => node.type_name() == type_name,
+            Term::Class(class_name) => node.class().and_then(|class| heap_dump.class_name(class)) == Some(class_name),
+            Term::Site(site) => node.allocation_site().map(|allocation_site| allocation_site.contains(site.as_str())).unwrap_or(false),
+            Term::MinMemsize(memsize) => node.memsize() >= *memsize,
+            Term::Generation(generation) => node.generation() == Some(*generation),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_parses_terms() {
+    let query = Query::parse("type:string  min_memsize:100 site:app.rb:7").unwrap();
+    assert_eq!(query.terms, vec![Term::Type(String::from("STRING")), Term::MinMemsize(100), Term::Site(String::from("app.rb:7"))]);
+  }
+
+  #[test]
+  fn it_fails_to_parse() {
+    assert!(Query::parse("STRING").is_err());
+    assert!(Query::parse("color:red").is_err());
+    assert!(Query::parse("min_memsize:lots").is_err());
+  }
+
+  #[test]
+  fn it_matches_nodes() {
+    let mut heap_dump = HeapDump::default();
+    heap_dump.add_line(String::from(r#"{"address":"0xc", "type":"CLASS", "name":"String", "memsize":0, "flags":{}}"#));
+    let node = Node::from_str(r#"{"address":"0xa", "type":"STRING", "class":"0xc", "memsize":40, "file":"app.rb", "line":7, "flags":{}}"#).unwrap();
+
+    assert!(Query::parse("").unwrap().matches(&heap_dump, &node));
+    assert!(Query::parse("type:STRING class:String site:app.rb:7").unwrap().matches(&heap_dump, &node));
+    assert!(!Query::parse("min_memsize:41").unwrap().matches(&heap_dump, &node));
+    assert!(!Query::parse("class:Array").unwrap().matches(&heap_dump, &node));
+  }
+}
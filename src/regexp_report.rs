@@ -0,0 +1,96 @@
+use std::collections::HashSet;
+use std::io;
+use std::io::Write;
+use super::heap_address::HeapAddress;
+use super::heap_dump::HeapDump;
+use super::object_space_2_6_0::imemo_type::ImemoType;
+use super::object_space_2_6_0::node::Node;
+use super::totals::Totals;
+
+// Regexps and MatchData. A MatchData keeps its whole target string alive,
+// however short the match. Regexp literals are held by the iseq they were
+// compiled into; a regexp no iseq references was built at runtime.
+pub struct RegexpReport {
+    matches: Vec<(HeapAddress, usize, String)>,
+    matches_by_site: Totals,
+    regexps_by_site: Totals,
+    dynamic_by_site: Totals,
+}
+
+impl RegexpReport {
+    pub fn new(heap_dump: &HeapDump) -> Self {
+        let literals: HashSet<HeapAddress> = heap_dump.objects()
+            .filter(|node| matches!(node, Node::Imemo(node_imemo) if node_imemo.imemo_type == ImemoType::Iseq))
+            .flat_map(|node| node.references().iter().cloned())
+            .collect();
+
+        let mut report = RegexpReport {
+            matches: vec![],
+            matches_by_site: Totals::default(),
+            regexps_by_site: Totals::default(),
+            dynamic_by_site: Totals::default(),
+        };
+        for node in heap_dump.objects() {
+            let site = node.allocation_site().unwrap_or_else(|| String::from("(unknown)"));
+            match node {
+                Node::Match(node_match) => {
+                    let target_bytes = Self::target_bytes(heap_dump, node);
+                    report.matches_by_site.add(site.clone(), target_bytes);
+                    report.matches.push((node_match.address, target_bytes, site));
+                }
+                Node::Regexp(node_regexp) => {
+                    report.regexps_by_site.add(site.clone(), node_regexp.memsize);
+                    if !literals.contains(&node_regexp.address) {
+                        report.dynamic_by_site.add(site, node_regexp.memsize);
+                    }
+                }
+                _ => {}
+            }
+        }
+        report.matches.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        report
+    }
+
+    // Memsize of the strings a MatchData references, i.e. its target
+    fn target_bytes(heap_dump: &HeapDump, node: &Node) -> usize {
+        node.references().iter()
+            .filter_map(|reference| heap_dump.get(*reference))
+            .filter(|referenced| matches!(referenced, Node::String(_)))
+            .map(|referenced| referenced.memsize())
+            .sum()
+    }
+
+    pub fn write(&self, out: &mut dyn Write, limit: usize) -> io::Result<()> {
+        Totals::write(out, "MatchData by allocation site (bytes of target strings)", &self.matches_by_site.by_memsize(limit))?;
+
+        writeln!(out, "MatchData with the largest target strings:")?;
+        for (address, target_bytes, site) in self.matches.iter().take(limit) {
+            writeln!(out, "  {:>10} bytes  0x{:x} {}", target_bytes, address, site)?;
+        }
+        writeln!(out)?;
+
+        Totals::write(out, "Regexps by allocation site", &self.regexps_by_site.by_memsize(limit))?;
+        Totals::write(out, "Regexps built at runtime by allocation site", &self.dynamic_by_site.by_count(limit))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::totals::Total;
+
+  #[test]
+  fn it_reports_matches_and_dynamic_regexps() {
+    let mut heap_dump = HeapDump::default();
+    heap_dump.add_line(String::from(r#"{"address":"0xa", "type":"IMEMO", "imemo_type":"iseq", "references":["0xb"], "memsize":300, "flags":{}}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xb", "type":"REGEXP", "class":"0x1", "memsize":500, "file":"app.rb", "line":1, "flags":{}}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xc", "type":"REGEXP", "class":"0x1", "memsize":700, "file":"app.rb", "line":2, "flags":{}}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xd", "type":"MATCH", "class":"0x2", "references":["0xe", "0xc"], "memsize":100, "file":"app.rb", "line":3, "flags":{}}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xe", "type":"STRING", "class":"0x3", "bytesize":4000, "memsize":4041, "flags":{}}"#));
+
+    let report = RegexpReport::new(&heap_dump);
+    assert_eq!(report.matches, vec![(0xd, 4041, String::from("app.rb:3"))]);
+    assert_eq!(report.regexps_by_site.by_memsize(10).len(), 2);
+    assert_eq!(report.dynamic_by_site.by_memsize(10), vec![(&String::from("app.rb:2"), &Total { count: 1, memsize: 700 })]);
+  }
+}
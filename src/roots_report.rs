@@ -0,0 +1,91 @@
+use std::collections::HashSet;
+use std::io;
+use std::io::Write;
+use super::dominator_tree::DominatorTree;
+use super::heap_dump::HeapDump;
+use super::heap_graph::{HeapGraph, Vertex, SUPER_ROOT};
+
+// Objects listed per root category
+const DOMINATED_LIMIT: usize = 5;
+
+pub struct RootCategory {
+    pub name: String,
+    pub direct: usize,
+    pub retained: usize,
+    // Largest objects only reachable through this root, as (retained size, description)
+    pub dominated: Vec<(usize, String)>,
+}
+
+// Root categories (vm, machine_context, global_tbl, finalizers, ...) with what
+// they hold on their own. Root vertices sit right below the synthetic root in the
+// dominator tree, so a root's retained size is what no other root can reach.
+pub struct RootsReport {
+    categories: Vec<RootCategory>,
+    total_retained: usize,
+}
+
+impl RootsReport {
+    pub fn new(heap_dump: &HeapDump) -> Self {
+        let graph = HeapGraph::new(heap_dump);
+        let tree = DominatorTree::new(heap_dump, &graph);
+
+        let mut categories: Vec<RootCategory> = heap_dump.roots().into_iter().map(|root| {
+            let index = graph.root_index(&root.root).unwrap();
+            let direct: HashSet<usize> = graph.successors(index).iter().cloned().collect();
+            RootCategory { name: root.root.clone(), direct: direct.len(), retained: tree.retained_size(index), dominated: vec![] }
+        }).collect();
+
+        for index in 0..graph.len() {
+            if let (Vertex::Object(_), Some(Vertex::Root(name))) = (graph.vertex(index), tree.idom(index).map(|idom| graph.vertex(idom))) {
+                let category = categories.iter_mut().find(|category| category.name == *name).unwrap();
+                category.dominated.push((tree.retained_size(index), graph.describe(heap_dump, index)));
+            }
+        }
+        for category in &mut categories {
+            category.dominated.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+            category.dominated.truncate(DOMINATED_LIMIT);
+        }
+        categories.sort_by(|a, b| b.retained.cmp(&a.retained).then(a.name.cmp(&b.name)));
+
+        let total_retained = tree.retained_size(SUPER_ROOT);
+        RootsReport { categories, total_retained }
+    }
+
+    pub fn write(&self, out: &mut dyn Write) -> io::Result<()> {
+        let owned: usize = self.categories.iter().map(|category| category.retained).sum();
+        writeln!(out, "Reachable: {} bytes, {} held by a single root category, {} shared between several",
+                 self.total_retained, owned, self.total_retained - owned)?;
+        writeln!(out)?;
+
+        writeln!(out, "  {:>10} {:>14}  root", "direct", "retained bytes")?;
+        for category in &self.categories {
+            writeln!(out, "  {:>10} {:>14}  {}", category.direct, category.retained, category.name)?;
+            for (retained, description) in &category.dominated {
+                writeln!(out, "  {:>10} {:>14}    {}", "", retained, description)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_splits_retained_size_by_root() {
+    let mut heap_dump = HeapDump::default();
+    heap_dump.add_line(String::from(r#"{"type":"ROOT", "root":"global_tbl", "references":["0xa", "0xc"]}"#));
+    heap_dump.add_line(String::from(r#"{"type":"ROOT", "root":"machine_context", "references":["0xb", "0xc"]}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xa", "type":"ARRAY", "length":1, "references":["0xd"], "memsize":100}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xb", "type":"ARRAY", "length":0, "memsize":10}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xc", "type":"ARRAY", "length":0, "memsize":1000}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xd", "type":"ARRAY", "length":0, "memsize":50}"#));
+
+    let report = RootsReport::new(&heap_dump);
+    let categories: Vec<(&str, usize, usize)> = report.categories.iter().map(|category| (category.name.as_str(), category.direct, category.retained)).collect();
+    assert_eq!(categories, vec![("global_tbl", 2, 150), ("machine_context", 2, 10)]);
+    assert_eq!(report.total_retained, 1160);
+    assert_eq!(report.categories[0].dominated, vec![(150, String::from("ARRAY 0xa"))]);
+  }
+}
@@ -0,0 +1,39 @@
+use serde::Serializer;
+use serde::ser::SerializeSeq;
+use crate::heap_address::HeapAddress;
+
+// Counterparts of DeserializeUtils, writing addresses the way dump_all prints them: "0x7fc9748b34f0"
+pub struct SerializeUtils {}
+impl SerializeUtils {
+    pub fn heap_address_to_hex(address: HeapAddress) -> String {
+        format!("0x{:x}", address)
+    }
+
+    pub fn to_hex<S>(address: &HeapAddress, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&SerializeUtils::heap_address_to_hex(*address))
+    }
+
+    pub fn to_hex_opt<S>(address: &Option<HeapAddress>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match address {
+            Some(address) => SerializeUtils::to_hex(address, serializer),
+            None => serializer.serialize_none()
+        }
+    }
+
+    pub fn to_hex_array<S>(addresses: &[HeapAddress], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut seq = serializer.serialize_seq(Some(addresses.len()))?;
+        for address in addresses {
+            seq.serialize_element(&SerializeUtils::heap_address_to_hex(*address))?;
+        }
+        seq.end()
+    }
+}
@@ -0,0 +1,123 @@
+use std::io;
+use std::io::Write;
+use super::heap_dump::HeapDump;
+use super::object_space_2_6_0::node::Node;
+use super::object_space_2_6_0::node_string::NodeString;
+use super::totals::Totals;
+
+const PREVIEW_CHARS: usize = 60;
+
+// String buffers: unused capacity, sharing, binary blobs and frozen counts.
+// Shared strings point into another string's buffer and cost only their slot.
+pub struct StringsReport<'a> {
+    strings: Vec<&'a NodeString>,
+    kinds: Totals,
+    slack: Totals,
+    blobs: Totals,
+    min_blob_bytes: usize,
+}
+
+impl<'a> StringsReport<'a> {
+    pub fn new(heap_dump: &'a HeapDump, min_blob_bytes: usize) -> Self {
+        let mut report = StringsReport {
+            strings: vec![],
+            kinds: Totals::default(),
+            slack: Totals::default(),
+            blobs: Totals::default(),
+            min_blob_bytes,
+        };
+
+        for node in heap_dump.objects() {
+            let node_string = match node {
+                Node::String(node_string) => node_string,
+                _ => continue
+            };
+            let site = node.allocation_site().unwrap_or_else(|| String::from("(unknown)"));
+
+            let buffer = if node_string.shared.unwrap_or(false) { "shared" }
+                else if node_string.embedded.unwrap_or(false) { "embedded" }
+                else { "own buffer" };
+            let frozen = if node_string.fstring.unwrap_or(false) { "fstring" }
+                else if node_string.frozen.unwrap_or(false) { "frozen" }
+                else { "unfrozen" };
+            report.kinds.add(format!("{}, {}", buffer, frozen), node_string.memsize);
+
+            if let (Some(capacity), Some(bytesize)) = (node_string.capacity, node_string.bytesize) {
+                if buffer == "own buffer" && capacity > bytesize {
+                    report.slack.add(site.clone(), capacity - bytesize);
+                }
+            }
+            if Self::is_blob(node_string, min_blob_bytes) {
+                report.blobs.add(site, node_string.bytesize.unwrap_or(0));
+            }
+            report.strings.push(node_string);
+        }
+        report
+    }
+
+    // dump_all leaves out the encoding of ASCII-8BIT strings, binary ones only have a bytesize
+    fn is_blob(node_string: &NodeString, min_blob_bytes: usize) -> bool {
+        match node_string.bytesize {
+            Some(bytesize) => node_string.encoding.is_none() && bytesize >= min_blob_bytes,
+            None => false
+        }
+    }
+
+    pub fn largest(&self, limit: usize) -> Vec<&'a NodeString> {
+        let mut sorted = self.strings.clone();
+        sorted.sort_by(|a, b| b.bytesize.cmp(&a.bytesize).then(a.address.cmp(&b.address)));
+        sorted.truncate(limit);
+        sorted
+    }
+
+    // Debug formatting escapes control characters, binary data stays on one line
+    pub fn preview(node_string: &NodeString) -> String {
+        match &node_string.value {
+            Some(value) if value.chars().count() > PREVIEW_CHARS => format!("{:?}...", value.chars().take(PREVIEW_CHARS).collect::<String>()),
+            Some(value) => format!("{:?}", value),
+            None => String::from("(no value)")
+        }
+    }
+
+    pub fn write(&self, out: &mut dyn Write, limit: usize) -> io::Result<()> {
+        Totals::write(out, "Strings by buffer and frozen state", &self.kinds.by_memsize(usize::MAX))?;
+        Totals::write(out, "Unused capacity by allocation site (slack bytes)", &self.slack.by_memsize(limit))?;
+        Totals::write(out, &format!("Binary strings of {} bytes or more by allocation site (bytesize)", self.min_blob_bytes), &self.blobs.by_memsize(limit))?;
+
+        writeln!(out, "Largest strings:")?;
+        for node_string in self.largest(limit) {
+            writeln!(out, "  {:>10} bytes  0x{:x} {} {}",
+                     node_string.bytesize.unwrap_or(0), node_string.address,
+                     node_string.encoding.as_deref().unwrap_or("?"), Self::preview(node_string))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::totals::Total;
+
+  #[test]
+  fn it_reports_string_buffers() {
+    let mut heap_dump = HeapDump::default();
+    heap_dump.add_line(String::from(r#"{"address":"0xa", "type":"STRING", "class":"0x1", "bytesize":100, "capacity":150, "value":"x", "encoding":"UTF-8", "memsize":191, "file":"app.rb", "line":1, "flags":{}}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xb", "type":"STRING", "class":"0x1", "shared":true, "bytesize":100, "capacity":150, "encoding":"UTF-8", "memsize":40, "flags":{}}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xc", "type":"STRING", "class":"0x1", "frozen":true, "embedded":true, "bytesize":3, "memsize":40, "flags":{}}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xd", "type":"STRING", "class":"0x1", "bytesize":5000, "memsize":5041, "file":"io.rb", "line":9, "flags":{}}"#));
+
+    let report = StringsReport::new(&heap_dump, 1024);
+    assert_eq!(report.slack.by_memsize(10), vec![(&String::from("app.rb:1"), &Total { count: 1, memsize: 50 })]);
+    assert_eq!(report.blobs.by_memsize(10), vec![(&String::from("io.rb:9"), &Total { count: 1, memsize: 5000 })]);
+    assert_eq!(report.kinds.by_count(10).len(), 3);
+    assert_eq!(report.largest(1)[0].address, 0xd);
+  }
+
+  #[test]
+  fn it_truncates_previews() {
+    let json = format!(r#"{{"address":"0xa", "class":"0x1", "value":"{}\n", "memsize":40, "flags":{{}}}}"#, "a".repeat(70));
+    let node_string: NodeString = serde_json::from_str(&json).unwrap();
+    assert_eq!(StringsReport::preview(&node_string), format!("{:?}...", "a".repeat(60)));
+  }
+}
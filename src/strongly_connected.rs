@@ -0,0 +1,131 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use super::heap_graph::HeapGraph;
+
+const UNVISITED: usize = usize::MAX;
+
+// Strongly connected components of the heap graph with Tarjan's algorithm,
+// iterative so that long reference chains can't overflow the stack.
+// Only components with more than one vertex, i.e. actual reference cycles, are kept.
+pub struct StronglyConnectedComponents {
+    components: Vec<Vec<usize>>,
+}
+
+impl StronglyConnectedComponents {
+    pub fn new(graph: &HeapGraph) -> Self {
+        let mut index = vec![UNVISITED; graph.len()];
+        let mut lowlink = vec![0; graph.len()];
+        let mut on_stack = vec![false; graph.len()];
+        let mut stack = vec![];
+        let mut components = vec![];
+        let mut next_index = 0;
+
+        for start in 0..graph.len() {
+            if index[start] != UNVISITED { continue; }
+            // (vertex, position of the next successor to visit)
+            let mut work = vec![(start, 0)];
+            index[start] = next_index;
+            lowlink[start] = next_index;
+            next_index += 1;
+            stack.push(start);
+            on_stack[start] = true;
+
+            while let Some((vertex, position)) = work.pop() {
+                if let Some(successor) = graph.successors(vertex).get(position).cloned() {
+                    work.push((vertex, position + 1));
+                    if index[successor] == UNVISITED {
+                        index[successor] = next_index;
+                        lowlink[successor] = next_index;
+                        next_index += 1;
+                        stack.push(successor);
+                        on_stack[successor] = true;
+                        work.push((successor, 0));
+                    } else if on_stack[successor] {
+                        lowlink[vertex] = lowlink[vertex].min(index[successor]);
+                    }
+                    continue;
+                }
+
+                if lowlink[vertex] == index[vertex] {
+                    let mut component = vec![];
+                    while let Some(member) = stack.pop() {
+                        on_stack[member] = false;
+                        component.push(member);
+                        if member == vertex { break; }
+                    }
+                    if component.len() > 1 {
+                        component.sort_unstable();
+                        components.push(component);
+                    }
+                }
+                if let Some((parent, _)) = work.last() {
+                    lowlink[*parent] = lowlink[*parent].min(lowlink[vertex]);
+                }
+            }
+        }
+        StronglyConnectedComponents { components }
+    }
+
+    pub fn components(&self) -> &[Vec<usize>] {
+        &self.components
+    }
+
+    // Shortest cycle through the first vertex of the component, starting and ending with it.
+    // Parents are only kept for the component's members, as there may be many components.
+    pub fn example_cycle(graph: &HeapGraph, component: &[usize]) -> Vec<usize> {
+        let members: HashSet<usize> = component.iter().cloned().collect();
+        let start = component[0];
+        let mut parents: HashMap<usize, usize> = HashMap::new();
+        let mut queue = VecDeque::new();
+        queue.push_back(start);
+
+        while let Some(vertex) = queue.pop_front() {
+            for successor in graph.successors(vertex) {
+                if *successor == start {
+                    let mut cycle = vec![start];
+                    let mut current = vertex;
+                    while current != start {
+                        cycle.push(current);
+                        current = parents[&current];
+                    }
+                    cycle.push(start);
+                    cycle.reverse();
+                    return cycle;
+                }
+                if members.contains(successor) && !parents.contains_key(successor) {
+                    parents.insert(*successor, vertex);
+                    queue.push_back(*successor);
+                }
+            }
+        }
+        vec![start]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::heap_dump::HeapDump;
+
+  #[test]
+  fn it_finds_cycles() {
+    // a -> b -> c -> a, c -> d, d -> e -> d
+    let mut heap_dump = HeapDump::default();
+    heap_dump.add_line(String::from(r#"{"type":"ROOT", "root":"vm", "references":["0xa"]}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xa", "type":"ARRAY", "length":1, "references":["0xb"], "memsize":10}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xb", "type":"ARRAY", "length":1, "references":["0xc"], "memsize":10}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xc", "type":"ARRAY", "length":2, "references":["0xa", "0xd"], "memsize":10}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xd", "type":"ARRAY", "length":1, "references":["0xe"], "memsize":10}"#));
+    heap_dump.add_line(String::from(r#"{"address":"0xe", "type":"ARRAY", "length":1, "references":["0xd"], "memsize":10}"#));
+
+    let graph = HeapGraph::new(&heap_dump);
+    let components = StronglyConnectedComponents::new(&graph);
+    let addresses: Vec<Vec<u64>> = components.components().iter()
+      .map(|component| component.iter().map(|index| graph.address(*index).unwrap()).collect())
+      .collect();
+    assert_eq!(addresses, vec![vec![0xd, 0xe], vec![0xa, 0xb, 0xc]]);
+
+    let cycle = StronglyConnectedComponents::example_cycle(&graph, &components.components()[1]);
+    let cycle: Vec<u64> = cycle.iter().map(|index| graph.address(*index).unwrap()).collect();
+    assert_eq!(cycle, vec![0xa, 0xb, 0xc, 0xa]);
+  }
+}
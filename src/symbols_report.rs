@@ -0,0 +1,144 @@
+use std::io;
+use std::io::Write;
+use super::heap_dump::HeapDump;
+use super::object_space_2_6_0::node::Node;
+use super::object_space_2_6_0::node_symbol::NodeSymbol;
+use super::totals::Totals;
+
+const PATTERN_CHARS: usize = 40;
+
+// Dynamic symbols, i.e. the ones created at runtime by to_sym, intern or
+// String#to_sym in a library. Static symbols from the source code never show
+// up as heap objects. Unbounded growth usually comes from symbolizing input.
+pub struct SymbolsReport<'a> {
+    symbols: Vec<&'a NodeSymbol>,
+    by_site: Totals,
+    by_pattern: Totals,
+}
+
+impl<'a> SymbolsReport<'a> {
+    pub fn new(heap_dump: &'a HeapDump) -> Self {
+        let mut report = SymbolsReport { symbols: vec![], by_site: Totals::default(), by_pattern: Totals::default() };
+        for node in heap_dump.objects() {
+            if let Node::Symbol(node_symbol) = node {
+                report.by_site.add(node.allocation_site().unwrap_or_else(|| String::from("(unknown)")), node_symbol.memsize);
+                report.by_pattern.add(Self::pattern(&node_symbol.value), node_symbol.memsize);
+                report.symbols.push(node_symbol);
+            }
+        }
+        report.symbols.sort_by_key(|node_symbol| node_symbol.address);
+        report
+    }
+
+    // The symbol with numbers replaced by # and hex strings by <hex>,
+    // so that user_1 and user_2 or session ids fall into the same group
+    pub fn pattern(value: &str) -> String {
+        let mut pattern = String::new();
+        let mut token = String::new();
+        for character in value.chars().chain(std::iter::once('\0')) {
+            if character.is_ascii_alphanumeric() {
+                token.push(character);
+                continue;
+            }
+            pattern.push_str(&Self::token_pattern(&token));
+            token.clear();
+            if character != '\0' {
+                pattern.push(character);
+            }
+        }
+        if pattern.chars().count() > PATTERN_CHARS {
+            pattern = format!("{}...", pattern.chars().take(PATTERN_CHARS).collect::<String>());
+        }
+        pattern
+    }
+
+    fn token_pattern(token: &str) -> String {
+        let has_digit = token.chars().any(|character| character.is_ascii_digit());
+        if token.len() >= 8 && has_digit && token.chars().all(|character| character.is_ascii_hexdigit()) {
+            return String::from("<hex>");
+        }
+        let mut pattern = String::new();
+        for character in token.chars() {
+            if !character.is_ascii_digit() {
+                pattern.push(character);
+            } else if !pattern.ends_with('#') {
+                pattern.push('#');
+            }
+        }
+        pattern
+    }
+
+    // Not something a programmer would write as a literal: long, numbered,
+    // hex ids or characters that aren't valid in an identifier
+    pub fn looks_generated(value: &str) -> bool {
+        let mut digits = 0;
+        for character in value.chars() {
+            digits = if character.is_ascii_digit() { digits + 1 } else { 0 };
+            if digits >= 3 { return true; }
+        }
+        value.chars().count() > PATTERN_CHARS
+            || Self::pattern(value).contains("<hex>")
+            || value.chars().any(|character| !(character.is_alphanumeric() || "_?!=@$".contains(character)))
+    }
+
+    pub fn write(&self, out: &mut dyn Write, limit: usize) -> io::Result<()> {
+        let memsize: usize = self.symbols.iter().map(|node_symbol| node_symbol.memsize).sum();
+        writeln!(out, "Dynamic symbols: {} ({} bytes)", self.symbols.len(), memsize)?;
+        writeln!(out)?;
+
+        Totals::write(out, "Dynamic symbols by allocation site", &self.by_site.by_count(limit))?;
+        Totals::write(out, "Dynamic symbols by pattern", &self.by_pattern.by_count(limit))?;
+
+        let generated: Vec<&&NodeSymbol> = self.symbols.iter().filter(|node_symbol| Self::looks_generated(&node_symbol.value)).collect();
+        writeln!(out, "Symbols that look generated: {}", generated.len())?;
+        for node_symbol in generated.iter().take(limit) {
+            writeln!(out, "  0x{:x} {:?}", node_symbol.address, node_symbol.value)?;
+        }
+        Ok(())
+    }
+
+    pub fn write_growth(&self, before: &SymbolsReport, out: &mut dyn Write, limit: usize) -> io::Result<()> {
+        writeln!(out)?;
+        writeln!(out, "Dynamic symbol growth since the earlier dump: {:+}", self.symbols.len() as i64 - before.symbols.len() as i64)?;
+        writeln!(out)?;
+
+        let mut by_site = self.by_site.change_since(&before.by_site);
+        by_site.truncate(limit);
+        Totals::write_change(out, "Change by allocation site", &by_site)?;
+        let mut by_pattern = self.by_pattern.change_since(&before.by_pattern);
+        by_pattern.truncate(limit);
+        Totals::write_change(out, "Change by pattern", &by_pattern)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::totals::Total;
+
+  #[test]
+  fn it_groups_symbols_by_pattern() {
+    assert_eq!(SymbolsReport::pattern("user_12"), "user_#");
+    assert_eq!(SymbolsReport::pattern("session-9f86d081884c7d65"), "session-<hex>");
+    assert_eq!(SymbolsReport::pattern("v2x10"), "v#x#");
+    assert!(SymbolsReport::looks_generated("user_123"));
+    assert!(SymbolsReport::looks_generated("content-type"));
+    assert!(!SymbolsReport::looks_generated("to_s"));
+    assert!(!SymbolsReport::looks_generated("valid?"));
+  }
+
+  #[test]
+  fn it_reports_growth() {
+    let line = |address: usize, value: &str| format!(r#"{{"address":"0x{:x}", "type":"SYMBOL", "class":"0x1", "frozen":true, "value":"{}", "memsize":40, "file":"params.rb", "line":7, "flags":{{}}}}"#, address, value);
+    let mut before = HeapDump::default();
+    before.add_line(line(0xa, "field_1"));
+    let mut after = HeapDump::default();
+    after.add_line(line(0xa, "field_1"));
+    after.add_line(line(0xb, "field_2"));
+
+    let before = SymbolsReport::new(&before);
+    let after = SymbolsReport::new(&after);
+    assert_eq!(after.by_pattern.by_count(10), vec![(&String::from("field_#"), &Total { count: 2, memsize: 80 })]);
+    assert_eq!(after.by_site.change_since(&before.by_site), vec![(String::from("params.rb:7"), 1, 40)]);
+  }
+}
@@ -0,0 +1,125 @@
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::io::Write;
+use super::heap_dump::HeapDump;
+use super::object_space_2_6_0::node::Node;
+
+#[derive(Default, PartialEq, Debug)]
+pub struct Total {
+    pub count: usize,
+    pub memsize: usize,
+}
+
+// Object count and memsize grouped by an arbitrary key (class, allocation site, ...)
+#[derive(Default)]
+pub struct Totals {
+    totals: HashMap<String, Total>,
+}
+
+impl Totals {
+    // Objects the key function returns None for are skipped
+    pub fn by<F>(heap_dump: &HeapDump, key: F) -> Self
+    where
+        F: Fn(&Node) -> Option<String>,
+    {
+        let mut totals = Totals::default();
+        for node in heap_dump.objects() {
+            if let Some(name) = key(node) {
+                totals.add(name, node.memsize());
+            }
+        }
+        totals
+    }
+
+    pub fn add(&mut self, key: String, memsize: usize) {
+        let total = self.totals.entry(key).or_default();
+        total.count += 1;
+        total.memsize += memsize;
+    }
+
+    // Largest memsize first, ties broken by key
+    pub fn by_memsize(&self, limit: usize) -> Vec<(&String, &Total)> {
+        let mut sorted: Vec<(&String, &Total)> = self.totals.iter().collect();
+        sorted.sort_by(|a, b| b.1.memsize.cmp(&a.1.memsize).then(a.0.cmp(b.0)));
+        sorted.truncate(limit);
+        sorted
+    }
+
+    // Largest count first, ties broken by key
+    pub fn by_count(&self, limit: usize) -> Vec<(&String, &Total)> {
+        let mut sorted: Vec<(&String, &Total)> = self.totals.iter().collect();
+        sorted.sort_by(|a, b| b.1.count.cmp(&a.1.count).then(a.0.cmp(b.0)));
+        sorted.truncate(limit);
+        sorted
+    }
+
+    // Count and memsize change per key since an earlier snapshot, largest growth first.
+    // Keys that didn't change are left out.
+    pub fn change_since(&self, before: &Totals) -> Vec<(String, i64, i64)> {
+        let empty = Total::default();
+        let keys: HashSet<&String> = before.totals.keys().chain(self.totals.keys()).collect();
+
+        let mut change: Vec<(String, i64, i64)> = keys.into_iter()
+            .map(|key| {
+                let before = before.totals.get(key).unwrap_or(&empty);
+                let after = self.totals.get(key).unwrap_or(&empty);
+                (key.clone(), after.count as i64 - before.count as i64, after.memsize as i64 - before.memsize as i64)
+            })
+            .filter(|(_, count, memsize)| *count != 0 || *memsize != 0)
+            .collect();
+        change.sort_by(|a, b| b.1.cmp(&a.1).then(b.2.cmp(&a.2)).then(a.0.cmp(&b.0)));
+        change
+    }
+
+    pub fn write_change(out: &mut dyn Write, title: &str, change: &[(String, i64, i64)]) -> io::Result<()> {
+        writeln!(out, "{}:", title)?;
+        if change.is_empty() {
+            writeln!(out, "  none")?;
+        }
+        for (key, count, memsize) in change {
+            writeln!(out, "  {:>+10} bytes {:>+8} objects  {}", memsize, count, key)?;
+        }
+        writeln!(out)
+    }
+
+    // Titled plain text listing, as used by the terminal reports
+    pub fn write(out: &mut dyn Write, title: &str, totals: &[(&String, &Total)]) -> io::Result<()> {
+        writeln!(out, "{}:", title)?;
+        if totals.is_empty() {
+            writeln!(out, "  none")?;
+        }
+        for (key, total) in totals {
+            writeln!(out, "  {:>10} bytes {:>8} objects  {}", total.memsize, total.count, key)?;
+        }
+        writeln!(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn it_sorts_totals() {
+    let mut totals = Totals::default();
+    totals.add(String::from("a"), 10);
+    totals.add(String::from("a"), 10);
+    totals.add(String::from("b"), 30);
+
+    assert_eq!(totals.by_memsize(1), vec![(&String::from("b"), &Total { count: 1, memsize: 30 })]);
+    assert_eq!(totals.by_count(1), vec![(&String::from("a"), &Total { count: 2, memsize: 20 })]);
+  }
+
+  #[test]
+  fn it_computes_changes() {
+    let mut before = Totals::default();
+    before.add(String::from("a"), 10);
+    before.add(String::from("b"), 10);
+    let mut after = Totals::default();
+    after.add(String::from("a"), 10);
+    after.add(String::from("c"), 30);
+    after.add(String::from("c"), 30);
+
+    assert_eq!(after.change_since(&before), vec![(String::from("c"), 2, 60), (String::from("b"), -1, -10)]);
+  }
+}
@@ -0,0 +1,180 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::io::{BufRead, Write};
+use serde_json::Value;
+use super::deserialize_utils::DeserializeUtils;
+use super::object_space_2_6_0::node::Node;
+
+// Line numbers kept per finding
+const EXAMPLE_LIMIT: usize = 5;
+// Fields holding heap addresses, or arrays of them
+const ADDRESS_FIELDS: &[&str] = &["address", "class", "default", "references"];
+
+#[derive(Default, Debug, PartialEq)]
+pub struct Finding {
+    pub count: usize,
+    pub lines: Vec<usize>,
+}
+
+impl Finding {
+    fn add(&mut self, line_number: usize) {
+        self.count += 1;
+        if self.lines.len() < EXAMPLE_LIMIT { self.lines.push(line_number); }
+    }
+}
+
+// Checks dump lines against the typed node schema, to notice a Ruby upgrade
+// changing the dump format before analyses silently go wrong. Violations are
+// records the typed structs can't load or with malformed addresses; unknown
+// fields are only reported, as they load into each node's extra map. They are
+// found from the raw keys, so records failing to load still show renamed fields.
+#[derive(Default)]
+pub struct Validator {
+    records: BTreeMap<String, usize>,
+    violations: BTreeMap<(String, String), Finding>,
+    unknown_types: BTreeMap<String, Finding>,
+    unknown_fields: BTreeMap<(String, String), Finding>,
+}
+
+impl Validator {
+    pub fn validate<R: BufRead>(reader: R) -> io::Result<Self> {
+        let mut validator = Validator::default();
+        for (index, line) in reader.lines().enumerate() {
+            validator.check_line(index + 1, &line?);
+        }
+        Ok(validator)
+    }
+
+    pub fn check_line(&mut self, line_number: usize, line: &str) {
+        if line.trim().is_empty() { return; }
+        let record = match serde_json::from_str::<Value>(line) {
+            Ok(Value::Object(record)) => record,
+            Ok(_) => return self.violation("(none)", "record is not a JSON object", line_number),
+            Err(_) => return self.violation("(none)", "invalid JSON", line_number)
+        };
+        let type_name = match record.get("type").and_then(Value::as_str) {
+            Some(type_name) => type_name,
+            None => return self.violation("(none)", "missing or non-string type", line_number)
+        };
+        *self.records.entry(String::from(type_name)).or_insert(0) += 1;
+
+        // Types without a typed struct declare no fields at all
+        if Node::declares_field(type_name, "type").is_none() {
+            return self.unknown_types.entry(String::from(type_name)).or_default().add(line_number);
+        }
+        if let Err(error) = Node::from_str(line) {
+            self.violation(type_name, &Self::describe_error(&error), line_number);
+        }
+        for (field, value) in &record {
+            if field != "type" && Node::declares_field(type_name, field) == Some(false) {
+                self.unknown_fields.entry((String::from(type_name), field.clone())).or_default().add(line_number);
+            }
+            if ADDRESS_FIELDS.contains(&field.as_str()) && !Self::is_address(value) {
+                self.violation(type_name, &format!("malformed address in {}", field), line_number);
+            }
+        }
+    }
+
+    // "0x7fc9748b34f0", an array of those for references, null for a missing class
+    fn is_address(value: &Value) -> bool {
+        match value {
+            Value::String(address) => address.starts_with("0x") && DeserializeUtils::hex_to_heap_address(address.clone()).is_some(),
+            Value::Array(addresses) => addresses.iter().all(|address| address.is_string() && Self::is_address(address)),
+            Value::Null => true,
+            _ => false
+        }
+    }
+
+    // Serde messages without the position, which differs on every line
+    fn describe_error(error: &serde_json::Error) -> String {
+        let message = error.to_string();
+        match message.find(" at line ") {
+            Some(position) => String::from(&message[..position]),
+            None => message
+        }
+    }
+
+    fn violation(&mut self, type_name: &str, message: &str, line_number: usize) {
+        self.violations.entry((String::from(type_name), String::from(message))).or_default().add(line_number);
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.violations.is_empty() && self.unknown_types.is_empty()
+    }
+
+    pub fn write(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "Records by type:")?;
+        for (type_name, count) in &self.records {
+            writeln!(out, "  {:>10}  {}", count, type_name)?;
+        }
+        writeln!(out)?;
+
+        writeln!(out, "Schema violations:")?;
+        Self::write_findings(out, self.violations.iter().map(|((type_name, message), finding)| (format!("{}: {}", type_name, message), finding)))?;
+        writeln!(out, "Unknown types:")?;
+        Self::write_findings(out, self.unknown_types.iter().map(|(type_name, finding)| (type_name.clone(), finding)))?;
+        writeln!(out, "Unknown fields:")?;
+        Self::write_findings(out, self.unknown_fields.iter().map(|((type_name, field), finding)| (format!("{}.{}", type_name, field), finding)))?;
+
+        writeln!(out, "{}", if self.is_valid() { "Valid" } else { "Invalid" })
+    }
+
+    fn write_findings<'a, I>(out: &mut dyn Write, findings: I) -> io::Result<()>
+    where
+        I: Iterator<Item = (String, &'a Finding)>,
+    {
+        let mut empty = true;
+        for (description, finding) in findings {
+            empty = false;
+            let lines: Vec<String> = finding.lines.iter().map(usize::to_string).collect();
+            writeln!(out, "  {:>10}  {} (lines {})", finding.count, description, lines.join(", "))?;
+        }
+        if empty {
+            writeln!(out, "  none")?;
+        }
+        writeln!(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn validate(lines: &[&str]) -> Validator {
+    let mut validator = Validator::default();
+    for (index, line) in lines.iter().enumerate() {
+      validator.check_line(index + 1, line);
+    }
+    validator
+  }
+
+  #[test]
+  fn it_accepts_valid_records() {
+    let validator = validate(&[
+      r#"{"type":"ROOT", "root":"vm", "references":["0x7fc969077fa8"]}"#,
+      r#"{"address":"0x7fc969077fa8", "type":"ARRAY", "length":0, "memsize":40}"#,
+    ]);
+    assert!(validator.is_valid());
+    assert!(validator.unknown_fields.is_empty());
+  }
+
+  #[test]
+  fn it_reports_violations_and_unknowns() {
+    let validator = validate(&[
+      r#"{"address":"0x1", "type":"ARRAY", "memsize":40}"#,
+      r#"{"address":"zz", "type":"ARRAY", "length":0, "memsize":40}"#,
+      r#"{"address":"0x2", "type":"ARRAY", "length":0, "memsize":40, "shape_id":7}"#,
+      r#"{"address":"0x3", "type":"ZOMBIE", "memsize":40}"#,
+      r#"not json"#,
+      r#"{"address":"0x5", "type":"ARRAY", "size":0, "memsize":40}"#,
+    ]);
+    assert!(!validator.is_valid());
+    assert_eq!(validator.violations.get(&(String::from("ARRAY"), String::from("missing field `length`"))), Some(&Finding { count: 2, lines: vec![1, 6] }));
+    assert_eq!(validator.violations.get(&(String::from("ARRAY"), String::from("malformed address in address"))), Some(&Finding { count: 1, lines: vec![2] }));
+    assert_eq!(validator.unknown_fields.get(&(String::from("ARRAY"), String::from("shape_id"))), Some(&Finding { count: 1, lines: vec![3] }));
+    assert_eq!(validator.unknown_types.get("ZOMBIE"), Some(&Finding { count: 1, lines: vec![4] }));
+    assert_eq!(validator.violations.get(&(String::from("(none)"), String::from("invalid JSON"))), Some(&Finding { count: 1, lines: vec![5] }));
+    // A renamed field shows up even though the record doesn't load
+    assert_eq!(validator.unknown_fields.get(&(String::from("ARRAY"), String::from("size"))), Some(&Finding { count: 1, lines: vec![6] }));
+  }
+}